@@ -0,0 +1,86 @@
+//! Times key searches inside a single node and point lookups through whole
+//! trees. Run with `cargo bench --bench search_modes`
+//!
+//! The node table compares `slice::binary_search` with two linear scans over
+//! node sized slices. The tree table compares the `SearchMode`s over a range
+//! of orders, for a tree that fits in cache and one that doesn't
+
+use btree_rust::{BTree, SearchMode};
+use std::hint::black_box;
+use std::time::Instant;
+
+const LOOKUPS: usize = 1 << 20;
+
+/// xorshift, so every run searches for the same keys
+fn random_keys(count: usize, mut state: u64) -> Vec<u64> {
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+        .collect()
+}
+
+/// Scan from the front, stopping at the first key that isn't smaller
+fn linear_search(keys: &[u64], key: &u64) -> Result<usize, usize> {
+    found_at(keys, key, keys.iter().take_while(|stored| *stored < key).count())
+}
+
+/// Count the smaller keys without stopping early, which has no data dependent
+/// branch to mispredict
+fn counting_search(keys: &[u64], key: &u64) -> Result<usize, usize> {
+    found_at(keys, key, keys.iter().filter(|stored| *stored < key).count())
+}
+
+fn found_at(keys: &[u64], key: &u64, idx: usize) -> Result<usize, usize> {
+    match keys.get(idx) {
+        Some(stored) if stored == key => Ok(idx),
+        _ => Err(idx),
+    }
+}
+
+fn nanos_per_lookup(search: impl Fn(&u64) -> bool, lookups: &[u64]) -> f64 {
+    let start = Instant::now();
+    let found = lookups.iter().filter(|key| search(key)).count();
+    black_box(found);
+    start.elapsed().as_nanos() as f64 / lookups.len() as f64
+}
+
+fn main() {
+    println!("{:>6} {:>12} {:>12} {:>12}", "keys", "binary ns", "linear ns", "counting ns");
+    for len in [2, 4, 8, 12, 16, 24, 32, 64] {
+        let mut keys = random_keys(len, 0x9e37_79b9_7f4a_7c15);
+        keys.sort_unstable();
+        let lookups: Vec<u64> = random_keys(LOOKUPS, 7).into_iter()
+            .map(|probe| keys[probe as usize % len] + probe % 2)
+            .collect();
+
+        let binary = nanos_per_lookup(|key| black_box(&keys).binary_search(key).is_ok(), &lookups);
+        let linear = nanos_per_lookup(|key| linear_search(black_box(&keys), key).is_ok(), &lookups);
+        let counting = nanos_per_lookup(|key| counting_search(black_box(&keys), key).is_ok(), &lookups);
+        println!("{:>6} {:>12.2} {:>12.2} {:>12.2}", len, binary, linear, counting);
+    }
+
+    for key_count in [1 << 10, 1 << 16] {
+        let keys = random_keys(key_count, 0x2545_f491_4f6c_dd1d);
+        let lookups: Vec<u64> = random_keys(LOOKUPS, 11).into_iter()
+            .map(|probe| keys[probe as usize % key_count] + probe % 2)
+            .collect();
+
+        println!("\n{} keys", key_count);
+        println!("{:>6} {:>12} {:>14}", "order", "binary ns", "interpol. ns");
+        for order in [4, 8, 16, 32, 64, 128, 256] {
+            let timings: Vec<f64> = [SearchMode::Binary, SearchMode::Interpolation]
+                .into_iter()
+                .map(|mode| {
+                    let mut tree = BTree::builder(order).search_mode(mode).build();
+                    tree.extend(keys.iter().copied());
+                    nanos_per_lookup(|key| tree.contains(key), &lookups)
+                })
+                .collect();
+            println!("{:>6} {:>12.1} {:>14.1}", order, timings[0], timings[1]);
+        }
+    }
+}
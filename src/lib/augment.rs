@@ -0,0 +1,309 @@
+use crate::node::{Node, NodeRef};
+use crate::range::{after_end, before_start};
+use crate::{BTree, BTreeBuilder};
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, RangeBounds};
+
+/// A summary kept for every subtree, e.g. the sum, minimum or maximum of its
+/// keys. Summaries are built from `of_key` for each key and `combine`, which
+/// has to be associative with `empty` as its identity, i.e. a monoid.
+///
+/// A tree keeps one augment, picked with `BTreeBuilder::augment`, and updates
+/// the summaries of the nodes each change touches. `BTree::summarize_range`
+/// then combines O(log n) of them to summarize any range of keys
+///
+/// ```
+/// use btree_rust::{Augment, BTree};
+///
+/// struct Max;
+///
+/// impl Augment<u32> for Max {
+///     type Summary = Option<u32>;
+///
+///     fn empty() -> Option<u32> { None }
+///     fn of_key(key: &u32) -> Option<u32> { Some(*key) }
+///     fn combine(left: &Option<u32>, right: &Option<u32>) -> Option<u32> {
+///         left.max(right).clone()
+///     }
+/// }
+///
+/// let mut tree = BTree::builder(8).augment::<Max>().build();
+/// tree.extend([4, 8, 15, 16, 23, 42]);
+/// assert_eq!(tree.summarize_range::<Max, _>(..20), Some(Some(16)));
+/// ```
+pub trait Augment<K>: 'static {
+    type Summary: Clone + 'static;
+
+    fn empty() -> Self::Summary;
+    fn of_key(key: &K) -> Self::Summary;
+    fn combine(left: &Self::Summary, right: &Self::Summary) -> Self::Summary;
+}
+
+/// Sums the keys of every subtree, which lets `BTree::sum_range` add up a
+/// range of keys in O(log n). Set with `BTreeBuilder::key_sums`
+pub struct KeySum;
+
+impl<K: Clone + Default + Add<Output = K> + 'static> Augment<K> for KeySum {
+    type Summary = K;
+
+    fn empty() -> K {
+        K::default()
+    }
+
+    fn of_key(key: &K) -> K {
+        key.clone()
+    }
+
+    fn combine(left: &K, right: &K) -> K {
+        left.clone() + right.clone()
+    }
+}
+
+/// Creates the empty summary of the tree's augment for a new node
+pub(crate) type NewSummary<K> = fn() -> NodeSummary<K>;
+
+/// The summary of a node's subtree, with the augment that computes it erased so
+/// that nodes don't need a type parameter for it
+pub(crate) type NodeSummary<K> = Box<dyn ErasedSummary<K>>;
+
+pub(crate) trait ErasedSummary<K> {
+    fn augment(&self) -> TypeId;
+    fn value(&self) -> &dyn Any;
+    /// Recompute from the node's keys and the summaries of its children
+    fn update(&mut self, keys: &[K], children: &[NodeRef<K>]);
+    /// An empty summary of the same augment, for a new node
+    fn empty(&self) -> NodeSummary<K>;
+    fn clone_box(&self) -> NodeSummary<K>;
+}
+
+struct Held<A, S> {
+    summary: S,
+    augment: PhantomData<fn() -> A>,
+}
+
+pub(crate) fn new_summary<K, A: Augment<K>>() -> NodeSummary<K> {
+    Box::new(Held { summary: A::empty(), augment: PhantomData::<fn() -> A> })
+}
+
+impl<K, S: Clone + 'static, A: Augment<K, Summary = S>> ErasedSummary<K> for Held<A, S> {
+    fn augment(&self) -> TypeId {
+        TypeId::of::<A>()
+    }
+
+    fn value(&self) -> &dyn Any {
+        &self.summary
+    }
+
+    /// Combine the summaries of the children with those of the keys between them
+    fn update(&mut self, keys: &[K], children: &[NodeRef<K>]) {
+        let mut summary = A::empty();
+        for idx in 0..keys.len().max(children.len()) {
+            if let Some(child) = children.get(idx) {
+                summary = A::combine(&summary, &summary_of::<K, A>(&child.borrow()));
+            }
+            if let Some(key) = keys.get(idx) {
+                summary = A::combine(&summary, &A::of_key(key));
+            }
+        }
+        self.summary = summary;
+    }
+
+    fn empty(&self) -> NodeSummary<K> {
+        new_summary::<K, A>()
+    }
+
+    fn clone_box(&self) -> NodeSummary<K> {
+        Box::new(Held { summary: self.summary.clone(), augment: self.augment })
+    }
+}
+
+impl<K> Clone for NodeSummary<K> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl<K> fmt::Debug for dyn ErasedSummary<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeSummary").field("augment", &self.augment()).finish_non_exhaustive()
+    }
+}
+
+/// The node's summary if it is kept for `A`, empty otherwise
+fn summary_of<K, A: Augment<K>>(node: &Node<K>) -> A::Summary {
+    get::<K, A>(node).cloned().unwrap_or_else(A::empty)
+}
+
+fn get<K, A: Augment<K>>(node: &Node<K>) -> Option<&A::Summary> {
+    let summary = node.summary.as_ref()?;
+    if summary.augment() != TypeId::of::<A>() {
+        return None;
+    }
+    summary.value().downcast_ref::<A::Summary>()
+}
+
+impl<K: Ord> BTreeBuilder<K> {
+    /// Keep a summary of every subtree computed by `A`, see `Augment`
+    pub fn augment<A: Augment<K>>(mut self) -> Self {
+        self.augment = Some(new_summary::<K, A>);
+        self
+    }
+
+    /// Keep the sum of every subtree's keys for `BTree::sum_range`
+    pub fn key_sums(self) -> Self
+        where K: Clone + Default + Add<Output = K> + 'static {
+        self.augment::<KeySum>()
+    }
+}
+
+impl<K: Ord> BTree<K> {
+    /// The summary of every key in the tree, `None` unless the tree was built
+    /// with the augment `A`
+    pub fn summary<A: Augment<K>>(&self) -> Option<A::Summary> {
+        get::<K, A>(&self.root.borrow()).cloned()
+    }
+
+    /// The summary of the keys inside `range`, `None` unless the tree was built
+    /// with the augment `A`. Subtrees that lie inside the range are covered by
+    /// their kept summary, so only the nodes on the paths to the two ends of
+    /// the range are visited
+    pub fn summarize_range<A: Augment<K>, R: RangeBounds<K>>(&self, range: R) -> Option<A::Summary> {
+        self.summary::<A>()?;
+        Some(summarize_range::<K, A, R>(&self.root, &range, false, false))
+    }
+
+    /// A new node with an empty summary if the tree keeps them
+    pub(crate) fn new_node(&self) -> NodeRef<K> {
+        let node = crate::node::node_utils::new_node_ref(self.order);
+        node.borrow_mut().summary = self.augment.map(|new_summary| new_summary());
+        node
+    }
+}
+
+/// Summarize the keys of the subtree inside `range`, `start_inside` and
+/// `end_inside` telling that an end of the range is known to lie outside the
+/// subtree
+fn summarize_range<K: Ord, A: Augment<K>, R: RangeBounds<K>>(
+    node: &NodeRef<K>, range: &R, start_inside: bool, end_inside: bool) -> A::Summary {
+    let node = node.borrow();
+    if start_inside && end_inside {
+        return summary_of::<K, A>(&node);
+    }
+
+    let start = if start_inside { 0 } else {
+        node.keys.partition_point(|key| before_start(range.start_bound(), key))
+    };
+    let end = if end_inside { node.keys.len() } else {
+        node.keys.partition_point(|key| !after_end(range.end_bound(), key))
+    };
+
+    // the keys `start..end` are in range, the children between them entirely
+    // and the ones at `start` and `end` in part
+    let mut summary = A::empty();
+    for idx in start..=end.max(start) {
+        if let Some(child) = node.children.get(idx) {
+            let child_summary = summarize_range::<K, A, R>(
+                child, range, start_inside || idx > start, end_inside || idx < end);
+            summary = A::combine(&summary, &child_summary);
+        }
+        if idx < end {
+            summary = A::combine(&summary, &A::of_key(&node.keys[idx]));
+        }
+    }
+    summary
+}
+
+/// Give every node of the subtree a summary and compute them
+pub(crate) fn seed_summaries<K: Ord>(node: &NodeRef<K>, new_summary: NewSummary<K>) {
+    node.borrow().children.iter().for_each(|child| seed_summaries(child, new_summary));
+    let mut node = node.borrow_mut();
+    node.summary = Some(new_summary());
+    node.recount();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Bound;
+    use std::rc::Rc;
+
+    /// Sum and number of keys, to check both the order and the grouping of combines
+    struct SumCount;
+
+    impl Augment<u64> for SumCount {
+        type Summary = (u64, usize);
+
+        fn empty() -> (u64, usize) { (0, 0) }
+        fn of_key(key: &u64) -> (u64, usize) { (*key, 1) }
+        fn combine(left: &(u64, usize), right: &(u64, usize)) -> (u64, usize) {
+            (left.0 + right.0, left.1 + right.1)
+        }
+    }
+
+    /// The keys in order, which only comes out right if combines keep their order
+    struct Concat;
+
+    impl Augment<u64> for Concat {
+        type Summary = Rc<Vec<u64>>;
+
+        fn empty() -> Rc<Vec<u64>> { Rc::new(Vec::new()) }
+        fn of_key(key: &u64) -> Rc<Vec<u64>> { Rc::new(vec![*key]) }
+        fn combine(left: &Rc<Vec<u64>>, right: &Rc<Vec<u64>>) -> Rc<Vec<u64>> {
+            Rc::new(left.iter().chain(right.iter()).copied().collect())
+        }
+    }
+
+    fn scan(tree: &BTree<u64>, range: (Bound<u64>, Bound<u64>)) -> (u64, usize) {
+        tree.range(range).fold((0, 0), |(sum, count), key| (sum + key, count + 1))
+    }
+
+    #[test]
+    fn summaries_follow_every_change() {
+        let mut tree = BTree::builder(4).augment::<SumCount>().build();
+        for key in (0..400).map(|k| k * 37 % 401) {
+            let _ = tree.add(key);
+        }
+        for key in (0..400).step_by(3) {
+            let _ = tree.delete(&key);
+        }
+        tree.remove_range(100..180);
+        tree.retain(|key| key % 7 != 0);
+        let mut cursor = tree.cursor_mut_at(&200);
+        cursor.remove_current();
+        let _ = cursor.insert_before(181);
+
+        let ranges = [
+            (Bound::Unbounded, Bound::Unbounded),
+            (Bound::Included(50), Bound::Excluded(250)),
+            (Bound::Excluded(99), Bound::Included(181)),
+            (Bound::Included(390), Bound::Unbounded),
+            (Bound::Included(300), Bound::Excluded(200)),
+        ];
+        for range in ranges {
+            assert_eq!(tree.summarize_range::<SumCount, _>(range), Some(scan(&tree, range)));
+        }
+        assert_eq!(tree.summary::<SumCount>().map(|(_, count)| count), Some(tree.len()));
+        assert_eq!(tree.clone().summary::<SumCount>(), tree.summary::<SumCount>());
+    }
+
+    #[test]
+    fn combines_keep_the_key_order() {
+        let mut tree = BTree::builder(3).augment::<Concat>().build();
+        tree.extend((0..100).rev());
+
+        let keys = tree.summarize_range::<Concat, _>(10..60).unwrap();
+        assert_eq!(*keys, (10..60).collect::<Vec<_>>());
+        assert_eq!(tree.drain().count(), 100);
+        assert_eq!(tree.summary::<Concat>().map(|keys| keys.len()), Some(0));
+    }
+
+    #[test]
+    fn other_augments_are_not_answered() {
+        let tree: BTree<u64> = BTree::builder(3).augment::<SumCount>().build();
+        assert_eq!(tree.summary::<SumCount>(), Some((0, 0)));
+        assert!(tree.summary::<Concat>().is_none());
+        assert!(BTree::<u64>::new(3).summarize_range::<SumCount, _>(..).is_none());
+    }
+}
@@ -1,60 +1,212 @@
-use crate::{Node, NodeRef};
-use std::cell::RefMut;
+use crate::node::node_utils::{recount, relink_children};
+use crate::NodeRef;
+use std::mem;
+use std::rc::Rc;
 
-/// The logic to delete a leaf node
-pub(super) fn delete_leaf(parent: NodeRef, child_index: usize) {
-    let mut parent = parent.borrow_mut();
+/// How `rebalance_child` fixed a child, with the index in the parent of the
+/// sibling pair that was rotated through or of the node they were merged into
+pub(super) enum Rebalanced {
+    Rotated { left: usize },
+    Merged { into: usize },
+}
+
+/// Fix the child at `child_index` of `parent` like `fix_child`, moving `spot`,
+/// a node and the index of a key in it, along with the key it points at
+pub(super) fn rebalance_child<K: Ord>(
+    parent: NodeRef<K>, child_index: usize, spot: &mut Option<(NodeRef<K>, usize)>) -> Rebalanced {
+    let (left, child, right) = {
+        let parent = parent.borrow();
+        let left = child_index.checked_sub(1).map(|idx| Rc::clone(&parent.children[idx]));
+        (left, Rc::clone(&parent.children[child_index]), parent.children.get(child_index + 1).cloned())
+    };
+    let left_len = left.as_ref().map_or(0, |left| left.borrow().keys.len());
+    let child_len = child.borrow().keys.len();
+
+    let rebalanced = fix_child(Rc::clone(&parent), child_index);
+    let Some((node, idx)) = spot.as_ref() else { return rebalanced };
+    let idx = *idx;
+    let is = |other: &Option<NodeRef<K>>| other.as_ref().is_some_and(|other| Rc::ptr_eq(other, node));
+    let in_child = Rc::ptr_eq(node, &child);
+    let in_parent = Rc::ptr_eq(node, &parent);
+
+    let moved = match rebalanced {
+        // the left sibling's last key went up, the parent's key down to the child's front
+        Rebalanced::Rotated { left: rotated } if rotated < child_index => {
+            if in_child {
+                Some((child, idx + 1))
+            } else if is(&left) && idx + 1 == left_len {
+                Some((parent, rotated))
+            } else if in_parent && idx == rotated {
+                Some((child, 0))
+            } else {
+                None
+            }
+        }
+        // the right sibling's first key went up, the parent's key down to the child's end
+        Rebalanced::Rotated { .. } => {
+            if is(&right) && idx == 0 {
+                Some((parent, child_index))
+            } else if is(&right) {
+                right.map(|right| (right, idx - 1))
+            } else if in_parent && idx == child_index {
+                Some((child, child_len))
+            } else {
+                None
+            }
+        }
+        // the parent's key and then the child's keys were appended to the left sibling
+        Rebalanced::Merged { into } if into < child_index => {
+            if in_child {
+                left.map(|left| (left, left_len + 1 + idx))
+            } else if in_parent && idx == into {
+                left.map(|left| (left, left_len))
+            } else if in_parent && idx > into {
+                Some((parent, idx - 1))
+            } else {
+                None
+            }
+        }
+        // the child's keys and the parent's key were put in front of the right sibling's
+        Rebalanced::Merged { .. } => {
+            if is(&right) {
+                right.map(|right| (right, child_len + 1 + idx))
+            } else if in_child {
+                right.map(|right| (right, idx))
+            } else if in_parent && idx == child_index {
+                right.map(|right| (right, child_len))
+            } else if in_parent && idx > child_index {
+                Some((parent, idx - 1))
+            } else {
+                None
+            }
+        }
+    };
+    if moved.is_some() {
+        *spot = moved;
+    }
+    rebalanced
+}
 
+/// The logic to fix a child that dropped under the minimum key count after a
+/// delete, by taking a key from a sibling or merging with one
+fn fix_child<K: Ord>(parent: NodeRef<K>, child_index: usize) -> Rebalanced {
     // Try and get a key from left
     if child_index != 0 {
         let left_idx = child_index - 1;
-        if shift_key_from_sibling(&mut parent, left_idx, child_index) {
-            return;
+        if shift_key_from_sibling(&parent, left_idx, child_index) {
+            return Rebalanced::Rotated { left: left_idx };
         }
     }
 
-    let max_size = parent.children.len();
-    if child_index < max_size {
+    let max_size = parent.borrow().children.len();
+    if child_index + 1 < max_size {
         let right_idx = child_index + 1;
-        if shift_key_from_sibling(&mut parent, right_idx, child_index) {
-            return;
+        if shift_key_from_sibling(&parent, right_idx, child_index) {
+            return Rebalanced::Rotated { left: child_index };
         }
     }
 
     // Try and merge with rhe left sibling
     if child_index != 0 {
-        let _ = parent.merge_children(child_index - 1, child_index);
-        return;
+        merge_children(&parent, child_index - 1, child_index);
+        return Rebalanced::Merged { into: child_index - 1 };
     }
 
     // Try and merge with the right sibling
-    let _ = parent.merge_children(child_index + 1, child_index);
+    merge_children(&parent, child_index + 1, child_index);
+    Rebalanced::Merged { into: child_index }
 }
 
 /// Shift a key from child in moved_from_idx into parent and the key in parent into
-fn shift_key_from_sibling(
-    parent: &mut RefMut<Node>, moved_from_idx: usize, moved_to_idx: usize) -> bool {
-    let move_from_child = parent.try_clone_child(moved_from_idx as isize).unwrap();
-    let mut move_from_child = move_from_child.borrow_mut();
+/// the child in moved_to_idx. For inner nodes the sibling's child next to the moved
+/// key goes along with it. Returns false if the sibling has no key to spare.
+///
+/// Keys and children are taken out of one node before the next is borrowed, so
+/// no two nodes are borrowed at once
+pub fn shift_key_from_sibling<K: Ord>(
+    parent: &NodeRef<K>, moved_from_idx: usize, moved_to_idx: usize) -> bool {
+    let (move_from_child, moved_to) = {
+        let parent = parent.borrow();
+        (Rc::clone(&parent.children[moved_from_idx]), Rc::clone(&parent.children[moved_to_idx]))
+    };
+    if moved_from_idx == moved_to_idx || !move_from_child.borrow().has_more_than_min_keys() {
+        return false;
+    }
 
-    if !move_from_child.has_more_than_min_keys() { return false; }
+    let from_right = moved_from_idx > moved_to_idx;
+    let parent_key_idx = if from_right { moved_to_idx } else { moved_from_idx };
 
-    let moved_to = parent.try_clone_child(moved_to_idx as isize).unwrap();
-    let mut moved_to = moved_to.borrow_mut();
+    // the sibling's edge key and the child next to it
+    let (move_from_key, moved_child) = {
+        let mut move_from_child = move_from_child.borrow_mut();
+        if from_right {
+            let child = (!move_from_child.is_leaf()).then(|| move_from_child.children.remove(0));
+            (move_from_child.keys.remove(0), child)
+        } else {
+            (move_from_child.keys.pop().unwrap(), move_from_child.children.pop())
+        }
+    };
 
-    let (parent_key_idx, child_key_idx_to_move) = if moved_from_idx > moved_to_idx {
-        // the moved_from is to the right
-        (moved_to_idx, 0)
-    } else {
-        (moved_from_idx, move_from_child.keys.len() - 1)
+    // the sibling's key takes the place of the parent's, which moves down
+    let parent_key_to_rotate = mem::replace(&mut parent.borrow_mut().keys[parent_key_idx], move_from_key);
+
+    {
+        let mut moved_to = moved_to.borrow_mut();
+        if from_right {
+            moved_to.keys.push(parent_key_to_rotate);
+            moved_to.children.extend(moved_child);
+        } else {
+            moved_to.keys.insert(0, parent_key_to_rotate);
+            if let Some(moved_child) = moved_child {
+                moved_to.children.insert(0, moved_child);
+            }
+        }
+    }
+
+    relink_children(&move_from_child);
+    relink_children(&moved_to);
+    recount(&move_from_child);
+    recount(&moved_to);
+    true
+}
+
+/// Merge the child at `merge_from` and the key between them into its sibling at
+/// `merge_into`, removing it from the parent. Like `shift_key_from_sibling` it
+/// borrows one node at a time
+pub fn merge_children<K: Ord>(parent: &NodeRef<K>, merge_into: usize, merge_from: usize) {
+    assert!(merge_into.abs_diff(merge_from) == 1, "Merged children must be next to each other");
+
+    let (parent_key, merge_into_child, merge_from_child) = {
+        let mut parent = parent.borrow_mut();
+        let parent_key = parent.keys.remove(merge_into.min(merge_from));
+        let merge_from_child = parent.children.remove(merge_from);
+        let merge_into_child = Rc::clone(&parent.children[merge_into.min(merge_from)]);
+        (parent_key, merge_into_child, merge_from_child)
+    };
+
+    let (mut keys, mut children) = {
+        let mut merge_from_child = merge_from_child.borrow_mut();
+        (mem::take(&mut merge_from_child.keys), mem::take(&mut merge_from_child.children))
     };
 
-    let move_from_key = move_from_child.keys.remove(child_key_idx_to_move);
-    let parent_key_to_rotate = parent.keys.remove(parent_key_idx);
+    {
+        let mut merge_into_child = merge_into_child.borrow_mut();
+        if merge_from < merge_into {
+            keys.push(parent_key);
+            keys.append(&mut merge_into_child.keys);
+            children.append(&mut merge_into_child.children);
+            merge_into_child.keys = keys;
+            merge_into_child.children = children;
+        } else {
+            merge_into_child.keys.push(parent_key);
+            merge_into_child.keys.append(&mut keys);
+            merge_into_child.children.append(&mut children);
+        }
+    }
 
-    parent.add_key(move_from_key);
-    moved_to.add_key(parent_key_to_rotate);
-    return true;
+    relink_children(&merge_into_child);
+    recount(&merge_into_child);
+    relink_children(parent);
 }
 
 #[cfg(test)]
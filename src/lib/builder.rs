@@ -0,0 +1,188 @@
+use crate::augment::NewSummary;
+use crate::changefeed::Changefeed;
+use crate::memory::order_for_node_bytes;
+use crate::node::node_utils::new_node_ref;
+use crate::node::search_mode::{binary_search, KeySearch};
+use crate::watch::Watchers;
+use crate::{default_order, BTree, InterpolationKey, InvariantCheck, Metrics, SearchMode};
+use std::fmt::Debug;
+
+/// Configures a `BTree` before it is created
+///
+/// ```
+/// use btree_rust::{BTree, SearchMode};
+///
+/// let mut tree = BTree::builder(64)
+///     .search_mode(SearchMode::Interpolation)
+///     .memory_budget(1 << 20)
+///     .build();
+/// tree.add(42_u64).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct BTreeBuilder<K> {
+    order: usize,
+    memory_budget: Option<usize>,
+    key_search: KeySearch<K>,
+    changefeed: bool,
+    pack_appends: bool,
+    invariant_check: Option<InvariantCheck<K>>,
+    pub(crate) augment: Option<NewSummary<K>>,
+}
+
+impl<K: Ord> BTreeBuilder<K> {
+    pub fn new(order: usize) -> Self {
+        Self {
+            order,
+            memory_budget: None,
+            key_search: binary_search,
+            changefeed: false,
+            pack_appends: false,
+            invariant_check: None,
+            augment: None,
+        }
+    }
+
+    /// Replace the order with the largest one whose nodes fit in `node_bytes`
+    pub fn target_node_bytes(mut self, node_bytes: usize) -> Self {
+        self.order = order_for_node_bytes::<K>(node_bytes);
+        self
+    }
+
+    /// Cap the estimated bytes used by the tree's nodes, see `BTree::set_memory_budget`
+    pub fn memory_budget(mut self, max_bytes: usize) -> Self {
+        self.memory_budget = Some(max_bytes);
+        self
+    }
+
+    /// Keep a log of every change, read with `BTree::changes_since`
+    pub fn changefeed(mut self) -> Self {
+        self.changefeed = true;
+        self
+    }
+
+    /// Fill nodes up when keys are inserted in increasing order, like
+    /// timestamps or auto-increment ids, instead of leaving every node split
+    /// off the right edge half full. Costs a walk up to the root per insert
+    /// at the end of the tree
+    pub fn pack_appends(mut self) -> Self {
+        self.pack_appends = true;
+        self
+    }
+
+    pub fn build(self) -> BTree<K> {
+        let root = new_node_ref(self.order);
+        root.borrow_mut().summary = self.augment.map(|new_summary| new_summary());
+        BTree {
+            root,
+            order: self.order,
+            node_count: 1,
+            key_count: 0,
+            height: 1,
+            memory_budget: self.memory_budget,
+            key_search: self.key_search,
+            watchers: Watchers::default(),
+            changefeed: self.changefeed.then(Changefeed::default),
+            invariant_check: self.invariant_check,
+            augment: self.augment,
+            metrics: Metrics::default(),
+            events: None,
+            pack_appends: self.pack_appends,
+        }
+    }
+}
+
+impl<K: Ord + Clone + Debug> BTreeBuilder<K> {
+    /// Run `BTree::check_invariants` after every insert, delete and clear, and
+    /// panic with the broken rule and the offending node's keys. Catches a
+    /// corrupting operation when it happens, at the cost of a full tree walk
+    /// per change, so it is meant for tests and debugging
+    pub fn check_invariants(mut self) -> Self {
+        self.invariant_check = Some(|tree| {
+            if let Err(err) = tree.check_invariants() {
+                panic!("B-tree invariant broken: {}", err);
+            }
+        });
+        self
+    }
+}
+
+impl<K: InterpolationKey> BTreeBuilder<K> {
+    /// Pick the strategy used to search for keys inside each node. Trees of
+    /// other key types always use binary search
+    pub fn search_mode(mut self, mode: SearchMode) -> Self {
+        self.key_search = mode.key_search();
+        self
+    }
+}
+
+impl<K: Ord> Default for BTreeBuilder<K> {
+    fn default() -> Self {
+        BTreeBuilder::new(default_order::<K>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{default_order, BTree, BTreeBuilder, SearchMode};
+
+    #[test]
+    fn default_trees_use_the_default_order() {
+        assert_eq!(BTree::<usize>::default().order, default_order::<usize>());
+        assert_eq!(BTreeBuilder::<u8>::default().build().order, default_order::<u8>());
+    }
+
+    #[test]
+    fn order_from_node_bytes() {
+        let small = BTree::<usize>::builder(3).target_node_bytes(256).build();
+        let large = BTree::<usize>::builder(3).target_node_bytes(4096).build();
+
+        assert!(small.order > 3);
+        assert!(large.order > small.order);
+    }
+
+    #[test]
+    fn larger_keys_get_a_smaller_order() {
+        assert!(default_order::<u8>() > default_order::<usize>());
+        assert!(default_order::<[u64; 8]>() < default_order::<usize>());
+    }
+
+    #[test]
+    fn interpolation_tree_finds_keys() {
+        let mut tree = BTree::builder(8)
+            .search_mode(SearchMode::Interpolation)
+            .build();
+
+        for key in (0..200).map(|k| k * 3) {
+            assert!(tree.add(key).is_ok());
+        }
+
+        for key in 0..600 {
+            let (status, _) = tree.find(&key);
+            assert_eq!(status.is_found(), key % 3 == 0, "Searching for {}", key);
+        }
+    }
+
+    #[test]
+    fn checked_tree_survives_valid_changes() {
+        let mut tree = BTree::builder(3).check_invariants().build();
+        for key in (0..100).map(|k| k * 37 % 101) {
+            let _ = tree.add(key);
+        }
+        for key in (0..100).step_by(3) {
+            let _ = tree.delete(&key);
+        }
+        tree.clear();
+    }
+
+    #[test]
+    #[should_panic(expected = "B-tree invariant broken: the tree records 5 keys but holds 4")]
+    fn checked_tree_panics_on_corruption() {
+        let mut tree = BTree::builder(3).check_invariants().build();
+        for key in 0..5 {
+            let _ = tree.add(key);
+        }
+
+        tree.key_count += 1;
+        let _ = tree.delete(&0);
+    }
+}
@@ -0,0 +1,482 @@
+use crate::node::{node_utils::new_node_ref, NodeRef};
+use crate::augment::seed_summaries;
+use crate::node::search_status::SearchStatus;
+use crate::{iter, BTree, BTreeError, Change, ChangeKind};
+use std::rc::Rc;
+
+impl<K: Ord> BTree<K> {
+    /// Build a tree bottom up from keys that are already sorted and unique,
+    /// without searching for or splitting any node
+    pub(crate) fn from_sorted_keys<I>(order: usize, keys: I) -> BTree<K>
+        where I: ExactSizeIterator<Item = K> {
+        BTree::from_sorted_keys_filled(order, order - 1, keys)
+    }
+
+    /// Same as `from_sorted_keys` with nodes of about `node_keys` keys, as
+    /// far as the minimum and maximum key counts allow
+    fn from_sorted_keys_filled<I>(order: usize, node_keys: usize, keys: I) -> BTree<K>
+        where I: ExactSizeIterator<Item = K> {
+        let mut tree = BTree::new(order);
+        let mut keys = keys;
+        if keys.len() == 0 { return tree; }
+        tree.key_count = keys.len();
+
+        // fill the leaves, every key between two leaves is kept for the level above
+        let sizes = node_sizes(keys.len(), order, node_keys);
+        let mut level: Vec<NodeRef<K>> = Vec::with_capacity(sizes.len());
+        let mut separators: Vec<K> = Vec::with_capacity(sizes.len() - 1);
+
+        for (idx, size) in sizes.iter().enumerate() {
+            let leaf = new_node_ref(order);
+            leaf.borrow_mut().keys.extend(keys.by_ref().take(*size));
+            leaf.borrow_mut().recount();
+            level.push(leaf);
+
+            if idx + 1 < sizes.len() {
+                separators.push(keys.next().unwrap());
+            }
+        }
+
+        let mut node_count = level.len();
+
+        // group each level under parents until a single root is left
+        while level.len() > 1 {
+            let sizes = node_sizes(separators.len(), order, node_keys);
+            let mut next_level: Vec<NodeRef<K>> = Vec::with_capacity(sizes.len());
+            let mut next_separators: Vec<K> = Vec::with_capacity(sizes.len() - 1);
+            let mut separators_iter = separators.into_iter();
+            let mut children = level.into_iter();
+
+            for (idx, size) in sizes.iter().enumerate() {
+                let parent = new_node_ref(order);
+                let mut parent_node = parent.borrow_mut();
+                parent_node.keys.extend(separators_iter.by_ref().take(*size));
+
+                for (child_idx, child) in children.by_ref().take(size + 1).enumerate() {
+                    let mut child_node = child.borrow_mut();
+                    child_node.parent = Rc::downgrade(&parent);
+                    child_node.index_in_parent = Some(child_idx);
+                    drop(child_node);
+                    parent_node.children.push(child);
+                }
+
+                parent_node.recount();
+                drop(parent_node);
+                next_level.push(parent);
+
+                if idx + 1 < sizes.len() {
+                    next_separators.push(separators_iter.next().unwrap());
+                }
+            }
+
+            node_count += next_level.len();
+            tree.height += 1;
+            level = next_level;
+            separators = next_separators;
+        }
+
+        tree.root = level.pop().unwrap();
+        tree.node_count = node_count;
+        tree
+    }
+
+    /// Rebuild the tree with every node as full as it can be, see `compact_to`
+    pub fn compact(&mut self) -> Result<(), BTreeError> {
+        self.compact_to(1.0)
+    }
+
+    /// Rebuild the tree with its nodes filled to `fill` of their capacity, to
+    /// recover the lookup speed and memory lost when heavy deletes leave nodes
+    /// near their minimum. The fill is kept between the minimum key count and
+    /// full nodes, and less than full leaves room for inserts before nodes
+    /// split again. The keys are moved and bulk loaded in O(n). Subscribers
+    /// and the changefeed see no change.
+    ///
+    /// Fails with `BudgetExceeded`, leaving the tree untouched, if the rebuilt
+    /// nodes would go over the memory budget
+    pub fn compact_to(&mut self, fill: f64) -> Result<(), BTreeError> {
+        let node_keys = ((self.order - 1) as f64 * fill).round() as usize;
+        self.check_rebuilt_budget(self.order, rebuilt_node_count(self.len(), self.order, node_keys))?;
+
+        let mut keys = Vec::with_capacity(self.len());
+        iter::take_keys(&self.root, &mut keys);
+        self.rebuild_filled(node_keys, keys.into_iter());
+        self.run_invariant_check();
+        Ok(())
+    }
+
+    /// Rebuild the tree with nodes of a different order, e.g. to move a tree
+    /// grown at a small order to one that fills cache lines. The keys are moved
+    /// out in order and bulk loaded into full nodes, so this is O(n) without a
+    /// single search or split. Subscribers and the changefeed see no change.
+    ///
+    /// Fails with `InvalidOrder` for an order below 3, and with `BudgetExceeded`
+    /// if the rebuilt nodes would go over the memory budget. The tree is left
+    /// untouched either way
+    pub fn change_order(&mut self, order: usize) -> Result<(), BTreeError> {
+        if order < 3 {
+            return Err(BTreeError::InvalidOrder);
+        }
+        self.check_rebuilt_budget(order, rebuilt_node_count(self.len(), order, order - 1))?;
+
+        let mut keys = Vec::with_capacity(self.len());
+        iter::take_keys(&self.root, &mut keys);
+        self.order = order;
+        self.rebuild_from_sorted_keys(keys.into_iter());
+        self.run_invariant_check();
+        Ok(())
+    }
+
+    /// Swap the tree's nodes for ones bulk built from `keys`, which have to be
+    /// sorted and unique. The settings, subscribers and changefeed stay as they are
+    pub(crate) fn rebuild_from_sorted_keys<I>(&mut self, keys: I)
+        where I: ExactSizeIterator<Item = K> {
+        self.rebuild_filled(self.order - 1, keys);
+    }
+
+    /// Same as `rebuild_from_sorted_keys` with nodes of about `node_keys` keys
+    fn rebuild_filled<I>(&mut self, node_keys: usize, keys: I)
+        where I: ExactSizeIterator<Item = K> {
+        let rebuilt = BTree::from_sorted_keys_filled(self.order, node_keys, keys);
+        self.root = rebuilt.root;
+        self.node_count = rebuilt.node_count;
+        self.key_count = rebuilt.key_count;
+        self.height = rebuilt.height;
+        if let Some(new_summary) = self.augment {
+            seed_summaries(&self.root, new_summary);
+        }
+        self.notify_root_change();
+    }
+}
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Add keys given in ascending order, skipping the ones already in the tree,
+    /// and return how many were added.
+    ///
+    /// Instead of searching from the root for every key, the path to the leaf
+    /// the last key went into is kept and each key only climbs as far as the
+    /// first node whose range holds it. Keys close together share most of their
+    /// descent, and the path is only searched again after a split changed it.
+    /// Keys out of order are still added, they just climb further. An empty
+    /// tree without a memory budget is bulk built instead
+    pub fn extend_from_sorted<I: IntoIterator<Item = K>>(&mut self, keys: I) -> usize {
+        if self.is_empty() && self.memory_budget.is_none() {
+            let mut keys: Vec<K> = keys.into_iter().collect();
+            keys.sort();
+            keys.dedup();
+            self.rebuild_from_sorted_keys(keys.iter().cloned());
+            self.run_invariant_check();
+            let added = keys.len();
+            for key in keys {
+                self.record_change(Change { key, kind: ChangeKind::Inserted });
+            }
+            return added;
+        }
+
+        let mut path: Vec<Step<K>> = Vec::new();
+        let mut added = 0;
+        for key in keys {
+            while path.last().is_some_and(|step| !step.holds(&path, &key)) {
+                path.pop();
+            }
+
+            let mut node = match path.last() {
+                Some(step) => Rc::clone(&step.node.borrow().children[step.child]),
+                None => Rc::clone(&self.root),
+            };
+            let idx = loop {
+                let status = node.borrow().search_keys(|keys| (self.key_search)(keys, &key));
+                let child = match status {
+                    SearchStatus::Found(_) => break None,
+                    SearchStatus::NotFound(idx) => match node.borrow().children.get(idx) {
+                        Some(child) => (Rc::clone(child), idx),
+                        None => break Some(idx),
+                    },
+                };
+                path.push(Step::new(&path, node, child.1));
+                node = child.0;
+            };
+            let Some(idx) = idx else { continue };
+
+            let node_count = self.node_count;
+            if self.insert_at(node, idx, key.clone()).is_err() {
+                continue;
+            }
+            if self.node_count != node_count {
+                path.clear();
+            }
+            added += 1;
+            self.record_change(Change { key, kind: ChangeKind::Inserted });
+        }
+        added
+    }
+}
+
+/// An inner node on the way down to a leaf and the child taken from it, see
+/// `extend_from_sorted`
+struct Step<K> {
+    node: NodeRef<K>,
+    child: usize,
+    /// The steps whose keys around the child they took are the nearest lower
+    /// and upper bounds of this child's keys, `None` for no bound
+    lower: Option<usize>,
+    upper: Option<usize>,
+}
+
+impl<K: Ord> Step<K> {
+    fn new(path: &[Step<K>], node: NodeRef<K>, child: usize) -> Self {
+        let level = path.len();
+        let lower = if child > 0 { Some(level) } else { path.last().and_then(|step| step.lower) };
+        let upper = if child < node.borrow().keys.len() {
+            Some(level)
+        } else {
+            path.last().and_then(|step| step.upper)
+        };
+        Step { node, child, lower, upper }
+    }
+
+    /// Whether `key` belongs under the child this step took
+    fn holds(&self, path: &[Step<K>], key: &K) -> bool {
+        let above_lower = self.lower.is_none_or(|level| {
+            let step = &path[level];
+            step.node.borrow().keys[step.child - 1] < *key
+        });
+        let below_upper = self.upper.is_none_or(|level| {
+            let step = &path[level];
+            *key < step.node.borrow().keys[step.child]
+        });
+        above_lower && below_upper
+    }
+}
+
+/// Spread `key_count` keys over nodes of about `node_keys` keys each, as far
+/// as the key count limits of an `order` allow, leaving one key between each
+/// pair of neighbouring nodes to be moved up a level
+///
+/// # Returns
+/// The number of keys each node gets, as even as possible so that no node
+/// drops below the minimum key count or goes over the maximum
+fn node_sizes(key_count: usize, order: usize, node_keys: usize) -> Vec<usize> {
+    let node_count = level_node_count(key_count, order, node_keys);
+    let node_keys = key_count - (node_count - 1);
+
+    (0..node_count)
+        .map(|idx| node_keys / node_count + usize::from(idx < node_keys % node_count))
+        .collect()
+}
+
+/// How many nodes `node_sizes` spreads `key_count` keys over
+fn level_node_count(key_count: usize, order: usize, node_keys: usize) -> usize {
+    let min_keys = order.div_ceil(2) - 1;
+    let most_nodes = ((key_count + 1) / (min_keys + 1)).max(1);
+    (key_count + 1).div_ceil(node_keys.max(1) + 1)
+        .min(most_nodes)
+        .max((key_count + 1).div_ceil(order))
+}
+
+/// How many nodes `from_sorted_keys_filled` builds for `key_count` keys,
+/// without building them
+fn rebuilt_node_count(key_count: usize, order: usize, node_keys: usize) -> usize {
+    let mut level = level_node_count(key_count, order, node_keys);
+    let mut node_count = level;
+    while level > 1 {
+        level = level_node_count(level - 1, order, node_keys);
+        node_count += level;
+    }
+    node_count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{node_sizes, rebuilt_node_count};
+    use crate::node::NodeRef;
+    use crate::{BTree, BTreeError};
+    use std::rc::Rc;
+
+    /// Check key counts, leaf depth and parent links, returning the leaf depth
+    fn check_node(node: &NodeRef<usize>, order: usize, depth: usize) -> usize {
+        let node_ref = node.borrow();
+        let min_keys = (order as f32 / 2.0).ceil() as usize - 1;
+
+        assert!(node_ref.keys.len() < order);
+        if !node_ref.is_root() {
+            assert!(node_ref.keys.len() >= min_keys, "Underfull node {:?}", node_ref.keys);
+        }
+
+        if node_ref.is_leaf() { return depth; }
+        assert_eq!(node_ref.children.len(), node_ref.keys.len() + 1);
+
+        let depths: Vec<usize> = node_ref.children.iter().enumerate()
+            .map(|(idx, child)| {
+                let child_ref = child.borrow();
+                assert!(Rc::ptr_eq(&child_ref.parent.upgrade().unwrap(), node));
+                assert_eq!(child_ref.index_in_parent, Some(idx));
+                drop(child_ref);
+                check_node(child, order, depth + 1)
+            })
+            .collect();
+
+        assert!(depths.iter().all(|d| *d == depths[0]));
+        depths[0]
+    }
+
+    fn collect_keys(tree: &BTree<usize>) -> Vec<usize> {
+        let mut keys = Vec::new();
+        let _ = tree.root.borrow().try_for_each_key(&mut |key| -> Result<(), ()> {
+            keys.push(*key);
+            Ok(())
+        });
+        keys
+    }
+
+    #[test]
+    fn node_sizes_respect_bounds() {
+        for order in 3..10 {
+            let min_keys = (order as f32 / 2.0).ceil() as usize - 1;
+
+            for key_count in order..200 {
+                for node_keys in 0..order {
+                    let sizes = node_sizes(key_count, order, node_keys);
+                    assert_eq!(sizes.iter().sum::<usize>() + sizes.len() - 1, key_count);
+                    assert!(sizes.iter().all(|s| *s >= min_keys && *s < order),
+                        "order {} keys {} sizes {:?}", order, key_count, sizes);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bulk_loaded_trees_are_valid() {
+        for order in 3..8 {
+            for key_count in 0..150 {
+                let tree = BTree::from_sorted_keys(order, 0..key_count);
+
+                assert_eq!(collect_keys(&tree), (0..key_count).collect::<Vec<usize>>());
+                check_node(&tree.root, order, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn sorted_batches_are_merged_in() {
+        for order in 3..8 {
+            let mut tree = BTree::builder(order).check_invariants().changefeed().build();
+            assert_eq!(tree.extend_from_sorted((0..300).step_by(3)), 100);
+            assert_eq!(tree.extend_from_sorted((0..300).filter(|k| k % 3 != 2)), 100);
+            assert_eq!(tree.extend_from_sorted([298, 5, 1000, 3]), 2);
+
+            let mut expected: Vec<usize> = (0..300).filter(|k| k % 3 != 2 || *k == 5).collect();
+            expected.push(1000);
+            assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+            assert_eq!(tree.changes_since(0).unwrap().count(), 202);
+        }
+
+        let mut tree = BTree::builder(3).memory_budget(1 << 20).build();
+        assert_eq!(tree.extend_from_sorted([3, 1, 2, 2]), 3);
+        assert_eq!(tree.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn order_is_changed_in_place() {
+        let mut tree = BTree::builder(3).check_invariants().key_sums().changefeed().build();
+        for key in (0..500_u64).rev() {
+            let _ = tree.add(key);
+        }
+        let height = tree.height();
+
+        tree.change_order(64).unwrap();
+        assert_eq!(tree.order(), 64);
+        assert!(tree.height() < height);
+        assert_eq!(tree.iter().collect::<Vec<_>>(), (0..500).collect::<Vec<_>>());
+        assert_eq!(tree.sum_range(100..200), (100..200).sum::<u64>());
+        assert_eq!(tree.changes_since(500).unwrap().count(), 0);
+
+        tree.change_order(4).unwrap();
+        assert!(tree.add(500).is_ok() && tree.delete(&0).is_ok());
+        assert_eq!(tree.len(), 500);
+    }
+
+    #[test]
+    fn change_order_checks_the_order_and_budget() {
+        let mut tree: BTree<usize> = BTree::builder(64).check_invariants().build();
+        tree.extend(0..1000);
+        assert!(matches!(tree.change_order(2), Err(BTreeError::InvalidOrder)));
+        assert!(matches!(tree.change_order(0), Err(BTreeError::InvalidOrder)));
+
+        tree.set_memory_budget(Some(tree.memory_usage()));
+        assert!(matches!(tree.change_order(3), Err(BTreeError::BudgetExceeded)));
+        assert_eq!(tree.order(), 64);
+        assert_eq!(tree.iter().collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+
+        tree.change_order(128).unwrap();
+        assert_eq!(tree.memory_usage(), tree.inspect().nodes().len() * crate::memory::node_bytes::<usize>(128));
+    }
+
+    #[test]
+    fn rebuilt_node_count_matches_the_build() {
+        for order in 3..8 {
+            for key_count in 0..150 {
+                for node_keys in 0..order {
+                    let tree = BTree::from_sorted_keys_filled(order, node_keys, 0..key_count);
+                    assert_eq!(rebuilt_node_count(key_count, order, node_keys), tree.inspect().nodes().len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compact_restores_full_nodes() {
+        let mut tree = BTree::builder(8).check_invariants().build();
+        tree.extend((0..2000).map(|key| key * 37 % 2000));
+        for key in (0..2000).filter(|key| key % 10 != 0) {
+            let _ = tree.delete(&key);
+        }
+        let (height, nodes) = (tree.height(), tree.inspect().nodes().len());
+
+        let leaf_keys = |tree: &BTree<usize>| -> Vec<usize> {
+            tree.inspect().nodes().iter()
+                .filter(|node| node.is_leaf())
+                .map(|node| node.keys.len())
+                .collect()
+        };
+
+        tree.compact().unwrap();
+        assert!(tree.height() < height);
+        assert!(tree.inspect().nodes().len() < nodes * 2 / 3);
+        assert!(leaf_keys(&tree).iter().all(|len| *len >= 6));
+        assert_eq!(tree.iter().collect::<Vec<_>>(), (0..2000).step_by(10).collect::<Vec<_>>());
+        check_node(&tree.root, 8, 0);
+
+        tree.compact_to(0.6).unwrap();
+        assert!(leaf_keys(&tree).iter().all(|len| (3..=4).contains(len)));
+        assert!(tree.add(1).is_ok());
+        tree.compact_to(0.0).unwrap();
+        check_node(&tree.root, 8, 0);
+    }
+
+    #[test]
+    fn compact_checks_the_budget() {
+        let mut tree: BTree<usize> = BTree::builder(8).check_invariants().build();
+        tree.extend(0..1000);
+        tree.compact().unwrap();
+        let usage = tree.memory_usage();
+
+        tree.set_memory_budget(Some(usage));
+        assert!(matches!(tree.compact_to(0.0), Err(BTreeError::BudgetExceeded)));
+        assert_eq!(tree.memory_usage(), usage);
+        assert_eq!(tree.iter().collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+        assert!(tree.compact().is_ok());
+    }
+
+    #[test]
+    fn bulk_loaded_tree_accepts_inserts() {
+        let mut tree = BTree::from_sorted_keys(4, (0..100).map(|k| k * 2));
+
+        for key in (0..100).map(|k| k * 2 + 1) {
+            assert!(tree.add(key).is_ok());
+        }
+
+        assert_eq!(collect_keys(&tree), (0..200).collect::<Vec<usize>>());
+        check_node(&tree.root, 4, 0);
+    }
+}
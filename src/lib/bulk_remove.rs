@@ -0,0 +1,400 @@
+use crate::node::node_utils::{recount_to_root, relink_children};
+use crate::node::NodeRef;
+use crate::range::{after_end, before_start};
+use crate::{iter, BTree, Change, ChangeKind, CursorMut};
+use std::mem;
+use std::ops::RangeBounds;
+use std::rc::Rc;
+
+impl<K: Ord> BTree<K> {
+    /// Keep only the keys `keep` returns true for, visiting each key once in
+    /// ascending order.
+    ///
+    /// Instead of deleting and rebalancing key by key, the kept keys are moved
+    /// out and the tree is bulk built again from them, so the whole call is O(n).
+    /// Subscribers and the changefeed see a delete for every removed key
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        let mut keys = Vec::with_capacity(self.len());
+        iter::take_keys(&self.root, &mut keys);
+        // the removed keys are only held on to when someone will see them
+        let observed = self.is_observed();
+        let mut removed = Vec::new();
+        let kept: Vec<K> = keys.into_iter()
+            .filter_map(|key| {
+                if keep(&key) {
+                    return Some(key);
+                }
+                if observed {
+                    removed.push(key);
+                }
+                None
+            })
+            .collect();
+
+        self.rebuild_from_sorted_keys(kept.into_iter());
+        self.run_invariant_check();
+        for key in removed {
+            self.record_change(Change { key, kind: ChangeKind::Deleted });
+        }
+    }
+}
+
+impl<K: Ord> BTree<K> {
+    /// Remove every key inside `range`, returning how many were removed.
+    ///
+    /// Whole subtrees inside the range are dropped without being rebalanced.
+    /// The nodes left along the two edges of the cut are joined level by level
+    /// into a single seam, which is then fixed with a split or a few rotations
+    /// and merges per level. That makes the call O(k + log n) for k removed keys
+    /// instead of a search and rebalance for every key. Subscribers and the
+    /// changefeed see a delete for every removed key
+    pub fn remove_range<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        let below = |key: &K| before_start(range.start_bound(), key);
+        let above = |key: &K| after_end(range.end_bound(), key);
+
+        // walk down to the first node holding a key in range
+        let mut node = Rc::clone(&self.root);
+        let (start, end) = loop {
+            let (start, end, child) = {
+                let node_ref = node.borrow();
+                let start = node_ref.keys.partition_point(below);
+                let end = node_ref.keys.partition_point(|key| !above(key));
+                (start, end, node_ref.try_clone_child(start as isize))
+            };
+
+            match child {
+                _ if start < end => break (start, end),
+                Some(child) if start == end => node = child,
+                _ => return 0,
+            }
+        };
+
+        let mut removed = Vec::new();
+        let seam = if node.borrow().is_leaf() {
+            removed.extend(node.borrow_mut().keys.drain(start..end));
+            vec![node]
+        } else {
+            self.cut_inner(node, start, end, &below, &above, &mut removed)
+        };
+        self.key_count -= removed.len();
+        for node in seam.iter().rev() {
+            recount_to_root(node);
+        }
+
+        #[cfg(feature = "merkle")]
+        seam.last().unwrap().borrow().invalidate_hash();
+
+        // joined nodes can hold up to twice the keys a node allows, split those
+        // bottom up first, then fix the ones left short
+        for node in seam.iter().rev() {
+            if node.borrow().is_key_overflowing() {
+                self.split_if_full(Rc::clone(node), 0);
+            }
+        }
+        self.fix_short_nodes(&seam);
+
+        self.run_invariant_check();
+        let count = removed.len();
+        for key in removed {
+            self.record_change(Change { key, kind: ChangeKind::Deleted });
+        }
+        count
+    }
+
+    /// Remove the keys `start..end` of the inner node `node` along with the
+    /// subtrees between them, then cut the rest of the range off the two
+    /// children left around the gap and join them into one
+    ///
+    /// # Returns
+    /// The joined nodes from `node` down to a leaf
+    fn cut_inner(
+        &mut self, node: NodeRef<K>, start: usize, end: usize,
+        below: &impl Fn(&K) -> bool, above: &impl Fn(&K) -> bool,
+        removed: &mut Vec<K>) -> Vec<NodeRef<K>> {
+        let (keys, children, left, right) = {
+            let mut node_ref = node.borrow_mut();
+            let keys: Vec<K> = node_ref.keys.drain(start..end).collect();
+            let children: Vec<NodeRef<K>> = node_ref.children.drain(start + 1..end).collect();
+            let right = node_ref.children.remove(start + 1);
+            (keys, children, Rc::clone(&node_ref.children[start]), right)
+        };
+        relink_children(&node);
+
+        // in order the removed keys and subtrees of `node` come between the
+        // ones cut off the left child and the ones cut off the right child
+        let mut left_cuts: Vec<Vec<K>> = Vec::new();
+        let mut middle = Vec::new();
+        let mut children = children.into_iter();
+        for (idx, key) in keys.into_iter().enumerate() {
+            if idx > 0 {
+                self.node_count -= iter::take_keys(&children.next().unwrap(), &mut middle);
+            }
+            middle.push(key);
+        }
+
+        let mut seam = vec![node];
+        let (mut left, mut right) = (left, right);
+        loop {
+            let mut left_cut = Vec::new();
+            let mut right_cut = Vec::new();
+            // the left node keeps its keys under the range, the right one those over it
+            let (left_keys, left_children) = {
+                let mut left_ref = left.borrow_mut();
+                let cut = left_ref.keys.partition_point(below);
+                let left_keys: Vec<K> = left_ref.keys.drain(cut..).collect();
+                let left_children: Vec<NodeRef<K>> = if left_ref.is_leaf() {
+                    Vec::new()
+                } else {
+                    left_ref.children.drain(cut + 1..).collect()
+                };
+                (left_keys, left_children)
+            };
+            let mut left_children = left_children.into_iter();
+            for key in left_keys {
+                left_cut.push(key);
+                if let Some(child) = left_children.next() {
+                    self.node_count -= iter::take_keys(&child, &mut left_cut);
+                }
+            }
+
+            let (right_keys, right_children) = {
+                let mut right_ref = right.borrow_mut();
+                let cut = right_ref.keys.partition_point(|key| !above(key));
+                let right_keys: Vec<K> = right_ref.keys.drain(..cut).collect();
+                let right_children: Vec<NodeRef<K>> = if right_ref.is_leaf() {
+                    Vec::new()
+                } else {
+                    right_ref.children.drain(..cut).collect()
+                };
+                (right_keys, right_children)
+            };
+            let mut right_children = right_children.into_iter();
+            for key in right_keys {
+                if let Some(child) = right_children.next() {
+                    self.node_count -= iter::take_keys(&child, &mut right_cut);
+                }
+                right_cut.push(key);
+            }
+
+            // join the right node into the left one, the two children at
+            // the gap are joined the same way a level down
+            let (mut keys, children) = {
+                let mut right_ref = right.borrow_mut();
+                (mem::take(&mut right_ref.keys), mem::take(&mut right_ref.children))
+            };
+            let next = {
+                let mut left_ref = left.borrow_mut();
+                let next = left_ref.children.last().cloned().zip(children.first().cloned());
+                left_ref.keys.append(&mut keys);
+                left_ref.children.extend(children.into_iter().skip(1));
+                next
+            };
+            relink_children(&left);
+            self.node_count -= 1;
+
+            left_cuts.push(left_cut);
+            middle.append(&mut right_cut);
+            seam.push(Rc::clone(&left));
+
+            match next {
+                Some((left_child, right_child)) => (left, right) = (left_child, right_child),
+                None => break,
+            }
+        }
+
+        // the deeper a cut the closer its keys are to the start of the range
+        for cut in left_cuts.into_iter().rev() {
+            removed.extend(cut);
+        }
+        removed.append(&mut middle);
+        seam
+    }
+
+    /// Fix the nodes of a path left short by a cut, from the top down so that
+    /// every node being fixed has a parent with siblings to draw from
+    pub(crate) fn fix_short_nodes(&mut self, path: &[NodeRef<K>]) {
+        for node in path {
+            while self.is_attached(node) && self.is_short(node) {
+                self.rebalance_after_delete(Rc::clone(node), &mut None);
+            }
+        }
+    }
+
+    /// Whether `node` is still part of the tree, it may have been merged away
+    fn is_attached(&self, node: &NodeRef<K>) -> bool {
+        let node_ref = node.borrow();
+        match node_ref.parent.upgrade() {
+            None => Rc::ptr_eq(node, &self.root),
+            Some(parent) => node_ref.index_in_parent
+                .and_then(|idx| parent.borrow().children.get(idx).cloned())
+                .is_some_and(|child| Rc::ptr_eq(&child, node)),
+        }
+    }
+
+    /// Whether `node` has fewer keys than allowed, for the root that means being
+    /// an inner node without keys
+    fn is_short(&self, node: &NodeRef<K>) -> bool {
+        let node_ref = node.borrow();
+        if Rc::ptr_eq(node, &self.root) {
+            node_ref.keys.is_empty() && !node_ref.children.is_empty()
+        } else {
+            node_ref.keys.len() < node_ref.min_keys()
+        }
+    }
+}
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Iterator that walks the keys in ascending order, removing and yielding
+    /// the ones `pred` returns true for.
+    ///
+    /// Keys are only removed as the iterator reaches them, so stopping early
+    /// leaves the rest in the tree. Every removal rebalances like `delete`
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, F>
+        where F: FnMut(&K) -> bool {
+        ExtractIf { cursor: CursorMut::first(self), pred }
+    }
+}
+
+/// Iterator removing the keys that match a predicate, see `BTree::extract_if`
+pub struct ExtractIf<'a, K, F> {
+    cursor: CursorMut<'a, K>,
+    pred: F,
+}
+
+impl<K: Ord + Clone, F: FnMut(&K) -> bool> Iterator for ExtractIf<'_, K, F> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        loop {
+            let pred = &mut self.pred;
+            if self.cursor.current_with(|key| pred(key))? {
+                // removing moves the cursor on to the following key
+                return self.cursor.remove_current();
+            }
+            self.cursor.advance();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BTree, BTreeMap, ChangeKind};
+    use std::ops::Bound;
+
+    #[test]
+    fn retain_keeps_matching_keys() {
+        let mut tree = BTree::builder(4).check_invariants().changefeed().build();
+        for key in (0..300).rev() {
+            let _ = tree.add(key);
+        }
+
+        let mut visited = Vec::new();
+        tree.retain(|key| {
+            visited.push(*key);
+            key % 5 == 0
+        });
+
+        assert_eq!(visited, (0..300).collect::<Vec<_>>());
+        assert_eq!(tree.iter().collect::<Vec<_>>(), (0..300).step_by(5).collect::<Vec<_>>());
+        assert_eq!(tree.len(), 60);
+        assert!(tree.add(301).is_ok() && tree.delete(&0).is_ok());
+
+        let deleted = tree.changes_since(300).unwrap()
+            .filter(|change| change.change.kind == ChangeKind::Deleted)
+            .count();
+        assert_eq!(deleted, 240 + 1);
+    }
+
+    #[test]
+    fn retain_nothing_or_everything() {
+        let mut tree = BTree::new(3);
+        tree.extend(0..50);
+
+        tree.retain(|_| true);
+        assert_eq!(tree.len(), 50);
+        assert!(tree.inspect().is_valid());
+
+        tree.retain(|_| false);
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 1);
+        assert!(tree.add(1).is_ok());
+    }
+
+    #[test]
+    fn extract_if_removes_lazily() {
+        let mut tree = BTree::builder(3).check_invariants().build();
+        tree.extend(0..100);
+
+        let mut expired = tree.extract_if(|key| key % 10 < 3);
+        assert_eq!(expired.by_ref().take(4).collect::<Vec<_>>(), vec![0, 1, 2, 10]);
+        drop(expired);
+        assert_eq!(tree.len(), 96);
+        assert!(tree.contains(&11));
+
+        let expired: Vec<usize> = tree.extract_if(|key| key % 10 < 3).collect();
+        assert_eq!(expired.len(), 26);
+        assert_eq!(expired.first(), Some(&11));
+        assert_eq!(tree.iter().collect::<Vec<_>>(),
+            (0..100).filter(|key| key % 10 >= 3).collect::<Vec<_>>());
+        assert_eq!(tree.extract_if(|_| true).count(), 70);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn remove_range_matches_a_filter() {
+        let ranges = [(0, 0), (10, 11), (10, 60), (0, 150), (37, 150), (0, 99), (73, 74), (2, 148)];
+        for order in 3..8 {
+            for (start, end) in ranges {
+                let mut tree = BTree::builder(order).check_invariants().changefeed().build();
+                // every key up to 150 but 114
+                for key in (0..150).map(|k| k * 37 % 151) {
+                    let _ = tree.add(key);
+                }
+                let (in_range, kept): (Vec<usize>, Vec<usize>) =
+                    (0..=150).filter(|k| *k != 114).partition(|k| (start..end).contains(k));
+
+                let removed = tree.remove_range(start..end);
+                assert_eq!(removed, in_range.len());
+                let msg = format!("order {} range {}..{}", order, start, end);
+                assert_eq!(tree.iter().collect::<Vec<_>>(), kept, "{}", msg);
+
+                let deleted: Vec<usize> = tree.changes_since(150).unwrap()
+                    .map(|change| change.change.key)
+                    .collect();
+                assert_eq!(deleted, in_range, "{}", msg);
+            }
+        }
+    }
+
+    #[test]
+    fn remove_range_bounds() {
+        let mut tree = BTree::builder(4).check_invariants().build();
+        tree.extend(0..100);
+
+        assert_eq!(tree.remove_range((Bound::Excluded(10), Bound::Included(20))), 10);
+        assert_eq!(tree.remove_range(90..), 10);
+        assert_eq!(tree.remove_range(..=5), 6);
+        assert_eq!(tree.remove_range((Bound::Included(50), Bound::Excluded(40))), 0);
+        assert_eq!(tree.remove_range(11..21), 0);
+        assert_eq!(tree.first(), Some(6));
+        assert_eq!(tree.last(), Some(89));
+        assert_eq!(tree.len(), 74);
+
+        assert_eq!(tree.remove_range(..), 74);
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 1);
+    }
+
+    #[test]
+    fn map_retain_sees_values() {
+        let mut map = BTreeMap::new(3);
+        for key in 0..40 {
+            let _ = map.add(key, key % 4);
+        }
+
+        map.retain(|key, value| *key < 20 && *value == 0);
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.get(&16), Some(0));
+        assert!(!map.contains_key(&20));
+    }
+}
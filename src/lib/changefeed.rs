@@ -0,0 +1,124 @@
+use crate::{BTree, Change};
+use std::collections::VecDeque;
+
+/// A change tagged with its position in the tree's changefeed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequencedChange<K> {
+    /// Starts at 1 and goes up by one with every change
+    pub seq: u64,
+    pub change: Change<K>,
+}
+
+/// Log of every change made to a tree, kept until it is trimmed
+#[derive(Debug, Clone)]
+pub(crate) struct Changefeed<K> {
+    changes: VecDeque<SequencedChange<K>>,
+    last_seq: u64,
+}
+
+impl<K> Default for Changefeed<K> {
+    fn default() -> Self {
+        Self { changes: VecDeque::new(), last_seq: 0 }
+    }
+}
+
+impl<K> Changefeed<K> {
+    pub fn push(&mut self, change: Change<K>) {
+        self.last_seq += 1;
+        self.changes.push_back(SequencedChange { seq: self.last_seq, change });
+    }
+}
+
+impl<K> BTree<K> {
+    /// Every change made after `seq`, oldest first. Pass 0 to read the whole feed
+    /// and then the last `seq` seen to continue from there
+    ///
+    /// # Returns
+    /// None if the tree was built without a changefeed or some of the changes
+    /// after `seq` have already been trimmed
+    pub fn changes_since(&self, seq: u64) -> Option<impl Iterator<Item = &SequencedChange<K>>> {
+        let feed = self.changefeed.as_ref()?;
+        let first_kept = feed.changes.front().map_or(feed.last_seq + 1, |change| change.seq);
+
+        // nothing follows u64::MAX, a reader can't have seen more than that
+        let next = seq.saturating_add(1);
+        if next < first_kept { return None; }
+
+        let skip = usize::try_from(next - first_kept).unwrap_or(usize::MAX);
+        Some(feed.changes.iter().skip(skip))
+    }
+
+    /// Sequence number of the latest change, 0 before the first one
+    pub fn last_sequence(&self) -> u64 {
+        self.changefeed.as_ref().map_or(0, |feed| feed.last_seq)
+    }
+
+    /// Forget the changes up to and including `seq` once every reader has seen them
+    pub fn trim_changes(&mut self, seq: u64) {
+        if let Some(feed) = self.changefeed.as_mut() {
+            while feed.changes.front().is_some_and(|change| change.seq <= seq) {
+                feed.changes.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BTree, Change, ChangeKind};
+
+    fn inserted(key: usize) -> Change<usize> {
+        Change { key, kind: ChangeKind::Inserted }
+    }
+
+    fn collect(tree: &BTree<usize>, seq: u64) -> Vec<(u64, Change<usize>)> {
+        tree.changes_since(seq).unwrap().map(|change| (change.seq, change.change)).collect()
+    }
+
+    #[test]
+    fn changes_are_numbered_in_order() {
+        let mut tree = BTree::builder(3).changefeed().build();
+        let _ = tree.add(3);
+        let _ = tree.add(1);
+        let _ = tree.add(1);
+        let _ = tree.delete(&3);
+
+        assert_eq!(tree.last_sequence(), 3);
+        assert_eq!(collect(&tree, 0), vec![
+            (1, inserted(3)),
+            (2, inserted(1)),
+            (3, Change { key: 3, kind: ChangeKind::Deleted }),
+        ]);
+        assert_eq!(collect(&tree, 2).len(), 1);
+        assert!(collect(&tree, 3).is_empty());
+        assert!(collect(&tree, u64::MAX).is_empty());
+    }
+
+    #[test]
+    fn trimmed_changes_are_gone() {
+        let mut tree = BTree::builder(3).changefeed().build();
+        for key in 0..5 {
+            let _ = tree.add(key);
+        }
+
+        tree.trim_changes(2);
+        assert!(tree.changes_since(0).is_none());
+        assert!(tree.changes_since(1).is_none());
+        assert_eq!(collect(&tree, 2), vec![(3, inserted(2)), (4, inserted(3)), (5, inserted(4))]);
+
+        tree.trim_changes(5);
+        assert!(collect(&tree, 5).is_empty());
+        assert!(collect(&tree, u64::MAX).is_empty());
+        let _ = tree.add(10);
+        assert_eq!(collect(&tree, 5), vec![(6, inserted(10))]);
+    }
+
+    #[test]
+    fn feed_is_off_by_default() {
+        let mut tree = BTree::new(3);
+        let _ = tree.add(1);
+
+        assert!(tree.changes_since(0).is_none());
+        assert_eq!(tree.last_sequence(), 0);
+    }
+}
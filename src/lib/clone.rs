@@ -0,0 +1,80 @@
+use crate::node::{Node, NodeRef};
+use crate::watch::Watchers;
+use crate::{BTree, Metrics};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+/// Copies every node, so the clone shares nothing with the original. The
+/// changefeed is copied along with the keys, subscribers are not
+impl<K: Ord + Clone> Clone for BTree<K> {
+    fn clone(&self) -> Self {
+        BTree {
+            root: clone_subtree(&self.root, Weak::new(), self.order),
+            order: self.order,
+            node_count: self.node_count,
+            key_count: self.key_count,
+            height: self.height,
+            memory_budget: self.memory_budget,
+            key_search: self.key_search,
+            watchers: Watchers::default(),
+            changefeed: self.changefeed.clone(),
+            invariant_check: self.invariant_check,
+            augment: self.augment,
+            metrics: Metrics::default(),
+            events: None,
+            pack_appends: self.pack_appends,
+        }
+    }
+}
+
+/// Copy `node` and everything below it, linking the copy to `parent`
+fn clone_subtree<K: Ord + Clone>(
+    node: &NodeRef<K>, parent: Weak<RefCell<Node<K>>>, order: usize) -> NodeRef<K> {
+    let node = node.borrow();
+
+    let mut copy = Node::new(order);
+    copy.parent = parent;
+    copy.index_in_parent = node.index_in_parent;
+    copy.keys.extend(node.keys.iter().cloned());
+    copy.size = node.size;
+    copy.node_count = node.node_count;
+    copy.summary = node.summary.clone();
+    #[cfg(feature = "merkle")]
+    copy.hash.set(node.hash.get());
+
+    let copy = Rc::new(RefCell::new(copy));
+    for child in node.children.iter() {
+        let child = clone_subtree(child, Rc::downgrade(&copy), order);
+        copy.borrow_mut().children.push(child);
+    }
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+
+    #[test]
+    fn clone_is_independent() {
+        let mut tree = BTree::new(3);
+        for key in 0..30 {
+            let _ = tree.add(key);
+        }
+
+        let mut copy = tree.clone();
+        assert!(copy.inspect().is_valid());
+        assert_eq!(copy.inspect().nodes().len(), tree.inspect().nodes().len());
+        assert_eq!(copy.iter().collect::<Vec<_>>(), tree.iter().collect::<Vec<_>>());
+
+        for key in 0..15 {
+            let _ = copy.delete(&key);
+        }
+        let _ = tree.add(100);
+
+        assert!(copy.inspect().is_valid() && tree.inspect().is_valid());
+        assert_eq!(copy.len(), 15);
+        assert_eq!(tree.len(), 31);
+        assert!(tree.contains(&0) && !copy.contains(&0));
+        assert!(!copy.contains(&100));
+    }
+}
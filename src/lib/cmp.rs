@@ -0,0 +1,60 @@
+use crate::BTree;
+use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
+
+/// Trees are equal when they hold the same keys, whatever their order or node
+/// layout. The keys are compared in order, copying them out of the nodes
+impl<K: Ord + Clone> PartialEq for BTree<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Ord + Clone> Eq for BTree<K> {}
+
+/// Hashes the key count followed by the keys in order, so trees that compare
+/// equal hash the same
+impl<K: Ord + Hash> Hash for BTree<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        let _ = self.root.borrow().try_for_each_key(&mut |key| -> Result<(), Infallible> {
+            key.hash(state);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn layout_does_not_matter() {
+        let mut grown = BTree::new(3);
+        for key in (0..200).rev() {
+            let _ = grown.add(key);
+        }
+        let bulk = BTree::from_slice(&(0..200).collect::<Vec<_>>());
+
+        assert_ne!(grown.height(), bulk.height());
+        assert_eq!(grown, bulk);
+        assert_eq!(hash_of(&grown), hash_of(&bulk));
+    }
+
+    #[test]
+    fn different_keys_are_not_equal() {
+        let tree = BTree::from([1, 2, 3]);
+        assert_ne!(tree, BTree::from([1, 2, 4]));
+        assert_ne!(tree, BTree::from([1, 2]));
+        assert_ne!(hash_of(&tree), hash_of(&BTree::from([1, 2, 4])));
+        assert_eq!(BTree::<usize>::new(3), BTree::new(5));
+    }
+}
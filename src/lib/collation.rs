@@ -0,0 +1,175 @@
+use crate::PrefixKey;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// An order for text keys other than their byte order, e.g. a locale's, used
+/// through `Collated` keys
+pub trait Collation {
+    fn compare(&self, a: &str, b: &str) -> Ordering;
+}
+
+/// A collation under which the texts starting with a prefix sort together, at
+/// or after the prefix itself, so `BTree::iter_prefix` can scan them as a range.
+/// Locale collations with contractions or ignorable characters usually can't
+/// promise this
+pub trait PrefixCollation: Collation {
+    /// The smallest text sorting after every text that starts with `prefix`,
+    /// `None` if no text does
+    fn prefix_successor(&self, prefix: &str) -> Option<String>;
+}
+
+/// A text key ordered by `collation`. Range bounds are `Collated` keys too, so
+/// they are compared the same way. Two keys the collation finds equal are the
+/// same key, and every key of a tree should use the same collation
+#[derive(Clone)]
+pub struct Collated<C> {
+    text: String,
+    collation: C,
+}
+
+impl<C: Collation> Collated<C> {
+    pub fn new(text: impl Into<String>, collation: C) -> Self {
+        Collated { text: text.into(), collation }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl<C> fmt::Debug for Collated<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.text)
+    }
+}
+
+impl<C: Collation> PartialEq for Collated<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<C: Collation> Eq for Collated<C> {}
+
+impl<C: Collation> PartialOrd for Collated<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Collation> Ord for Collated<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.collation.compare(&self.text, &other.text)
+    }
+}
+
+impl<C: PrefixCollation + Clone> PrefixKey for Collated<C> {
+    fn prefix_successor(&self) -> Option<Self> {
+        let text = self.collation.prefix_successor(&self.text)?;
+        Some(Collated { text, collation: self.collation.clone() })
+    }
+}
+
+#[cfg(feature = "icu")]
+pub use icu::IcuCollation;
+
+#[cfg(feature = "icu")]
+mod icu {
+    use super::Collation;
+    use icu_collator::{Collator, CollatorOptions};
+    use icu_locid::Locale;
+    use icu_provider::DataLocale;
+    use std::cmp::Ordering;
+    use std::rc::Rc;
+
+    /// A locale's order from ICU's collator, e.g. `"sv"` sorts "ö" after "z"
+    /// and `"de"` next to "o". Keys share the collator, cloning one is cheap
+    #[derive(Clone)]
+    pub struct IcuCollation(Rc<Collator>);
+
+    impl IcuCollation {
+        /// The collation for a BCP 47 locale like `"sv"` or `"de-AT"`, `None`
+        /// if the locale doesn't parse. Locales without data of their own use
+        /// the root collation
+        pub fn new(locale: &str) -> Option<Self> {
+            let locale: Locale = locale.parse().ok()?;
+            let collator = Collator::try_new(&DataLocale::from(&locale), CollatorOptions::new()).ok()?;
+            Some(IcuCollation(Rc::new(collator)))
+        }
+    }
+
+    impl Collation for IcuCollation {
+        fn compare(&self, a: &str, b: &str) -> Ordering {
+            self.0.compare(a, b)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BTree, Range};
+
+    /// ASCII letters without regard to case, "Pear" and "pear" are the same key
+    #[derive(Clone, Copy)]
+    struct IgnoreCase;
+
+    impl Collation for IgnoreCase {
+        fn compare(&self, a: &str, b: &str) -> Ordering {
+            let fold = |text: &str| text.bytes().map(|byte| byte.to_ascii_lowercase()).collect::<Vec<_>>();
+            fold(a).cmp(&fold(b))
+        }
+    }
+
+    impl PrefixCollation for IgnoreCase {
+        fn prefix_successor(&self, prefix: &str) -> Option<String> {
+            let mut successor = prefix.to_ascii_lowercase().prefix_successor()?;
+            // upper case letters sort as lower case ones, '@' is followed by '['
+            if successor.ends_with(|last: char| last.is_ascii_uppercase()) {
+                successor.pop();
+                successor.push('[');
+            }
+            Some(successor)
+        }
+    }
+
+    fn key(text: &str) -> Collated<IgnoreCase> {
+        Collated::new(text, IgnoreCase)
+    }
+
+    #[test]
+    fn keys_follow_the_collation() {
+        let mut tree = BTree::new(3);
+        for word in ["pear", "Apple", "fig", "Peach", "banana", "PEA", "pE@", "pe[", "pf"] {
+            assert!(tree.add(key(word)).is_ok());
+        }
+        assert!(tree.add(key("FIG")).is_err());
+
+        let words = |keys: Range<'_, Collated<IgnoreCase>>| keys.map(Collated::into_string).collect::<Vec<_>>();
+        assert_eq!(words(tree.iter()), ["Apple", "banana", "fig", "pE@", "pe[", "PEA", "Peach", "pear", "pf"]);
+        assert_eq!(words(tree.range(key("B")..key("PEACH"))), ["banana", "fig", "pE@", "pe[", "PEA"]);
+        assert_eq!(words(tree.iter_prefix(&key("Pea"))), ["PEA", "Peach", "pear"]);
+        assert_eq!(words(tree.iter_prefix(&key("pe@"))), ["pE@"]);
+        assert!(tree.contains(&key("BANANA")));
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn icu_orders_per_locale() {
+        let sorted = |locale: &str| {
+            let collation = IcuCollation::new(locale).unwrap();
+            let tree: BTree<_> = ["zebra", "öl", "apple", "ärm"].into_iter()
+                .map(|word| Collated::new(word, collation.clone()))
+                .collect();
+            tree.iter().map(Collated::into_string).collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted("sv"), ["apple", "zebra", "ärm", "öl"]);
+        assert_eq!(sorted("de"), ["apple", "ärm", "öl", "zebra"]);
+        assert!(IcuCollation::new("not a locale!").is_none());
+    }
+}
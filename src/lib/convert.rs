@@ -0,0 +1,139 @@
+use crate::map::MapEntry;
+use crate::{default_order, BTree, BTreeMap};
+use std::collections::{self, BTreeSet};
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Build a tree of `default_order::<K>()` from the keys in any order, dropping duplicates
+    pub fn from_slice(keys: &[K]) -> BTree<K> {
+        if keys.windows(2).all(|pair| pair[0] < pair[1]) {
+            return BTree::from_sorted_keys(default_order::<K>(), keys.iter().cloned());
+        }
+
+        let mut keys = keys.to_vec();
+        keys.sort_unstable();
+        keys.dedup();
+        BTree::from_sorted_keys(default_order::<K>(), keys.into_iter())
+    }
+}
+
+impl<K: Ord + Clone, const N: usize> From<[K; N]> for BTree<K> {
+    fn from(keys: [K; N]) -> Self {
+        BTree::from_slice(&keys)
+    }
+}
+
+/// Bulk builds a tree of `default_order::<K>()` straight from the set's sorted keys
+impl<K: Ord> From<BTreeSet<K>> for BTree<K> {
+    fn from(set: BTreeSet<K>) -> Self {
+        BTree::from_sorted_keys(default_order::<K>(), set.into_iter())
+    }
+}
+
+impl<K: Ord> From<BTree<K>> for BTreeSet<K> {
+    fn from(tree: BTree<K>) -> Self {
+        // the keys come out sorted which lets the set bulk build as well
+        BTreeSet::from_iter(tree)
+    }
+}
+
+/// Bulk builds a map of `default_order` for its entries straight from the std
+/// map's sorted entries
+impl<K: Ord, V> From<collections::BTreeMap<K, V>> for BTreeMap<K, V> {
+    fn from(map: collections::BTreeMap<K, V>) -> Self {
+        let entries = map.into_iter().map(|(key, value)| MapEntry { key, value });
+        BTreeMap { tree: BTree::from_sorted_keys(default_order::<MapEntry<K, V>>(), entries) }
+    }
+}
+
+impl<K: Ord, V> From<BTreeMap<K, V>> for collections::BTreeMap<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        // the entries come out sorted which lets the std map bulk build as well
+        map.tree.into_iter().map(|entry| (entry.key, entry.value)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::MapEntry;
+    use crate::{default_order, BTree, BTreeMap};
+    use std::collections::{self, BTreeSet};
+
+    #[test]
+    fn set_round_trip() {
+        let set: BTreeSet<usize> = (0..500).map(|k| k * 7 % 1009).collect();
+
+        let tree = BTree::from(set.clone());
+        assert_eq!(tree.order, default_order::<usize>());
+
+        let back: BTreeSet<usize> = tree.into();
+        assert_eq!(back, set);
+    }
+
+    #[test]
+    fn map_round_trip() {
+        let map: collections::BTreeMap<usize, String> =
+            (0..500).map(|k| k * 7 % 1009).map(|key| (key, key.to_string())).collect();
+
+        let converted = BTreeMap::from(map.clone());
+        assert_eq!(converted.order(), default_order::<MapEntry<usize, String>>());
+        assert_eq!(converted.len(), map.len());
+        assert_eq!(converted.get(&14), Some(String::from("14")));
+        assert!(converted.tree.inspect().is_valid());
+
+        let back: collections::BTreeMap<usize, String> = converted.into();
+        assert_eq!(back, map);
+    }
+
+    #[test]
+    fn converted_map_is_editable() {
+        let mut map = BTreeMap::from(collections::BTreeMap::from([(4, 'a'), (8, 'b'), (15, 'c')]));
+
+        assert!(map.add(16, 'd').is_ok());
+        assert!(map.add(8, 'e').is_err());
+        assert_eq!(map.remove(&4), Some((4, 'a')));
+        assert_eq!(collections::BTreeMap::from(map), collections::BTreeMap::from([(8, 'b'), (15, 'c'), (16, 'd')]));
+
+        let empty = BTreeMap::<usize, char>::from(collections::BTreeMap::new());
+        assert!(empty.is_empty());
+        assert!(collections::BTreeMap::from(empty).is_empty());
+    }
+
+    #[test]
+    fn converted_tree_is_searchable() {
+        let mut tree = BTree::from(BTreeSet::from([4, 8, 15, 16, 23, 42]));
+
+        assert!(tree.find(&15).0.is_found());
+        assert!(!tree.find(&14).0.is_found());
+        assert!(tree.add(14).is_ok());
+        assert!(tree.add(42).is_err());
+    }
+
+    #[test]
+    fn slice_is_sorted_and_deduplicated() {
+        let tree = BTree::from_slice(&[9, 3, 3, 7, 1, 9, 0]);
+        assert_eq!(Vec::from_iter(BTreeSet::from(tree)), vec![0, 1, 3, 7, 9]);
+
+        let sorted: Vec<usize> = (0..100).collect();
+        let tree = BTree::from_slice(&sorted);
+        assert_eq!(Vec::from_iter(BTreeSet::from(tree)), sorted);
+    }
+
+    #[test]
+    fn array_conversion() {
+        let tree = BTree::from([5, 1, 4, 1, 3]);
+
+        assert!(tree.find(&4).0.is_found());
+        assert!(!tree.find(&2).0.is_found());
+        assert_eq!(Vec::from_iter(BTreeSet::from(tree)), vec![1, 3, 4, 5]);
+
+        let empty: [usize; 0] = [];
+        assert!(BTree::from(empty).root.borrow().keys.is_empty());
+    }
+
+    #[test]
+    fn empty_set_gives_empty_tree() {
+        let tree = BTree::<usize>::from(BTreeSet::new());
+        assert!(tree.root.borrow().keys.is_empty());
+        assert!(BTreeSet::from(tree).is_empty());
+    }
+}
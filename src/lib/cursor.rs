@@ -0,0 +1,717 @@
+use crate::node::search_mode::KeySearch;
+use crate::node::NodeRef;
+use crate::{BTree, BTreeError, Change, ChangeKind};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+impl<K: Ord> BTree<K> {
+    /// A cursor on the first key at or after `key`, or past the last key if
+    /// every key is smaller
+    pub fn cursor_at(&self, key: &K) -> Cursor<'_, K> {
+        let mut position = Position::new(self.key_search);
+        position.seek(&self.root, key);
+        Cursor { root: Rc::clone(&self.root), position, tree: PhantomData }
+    }
+
+    /// A cursor that can also add and remove keys, placed like `cursor_at`
+    pub fn cursor_mut_at(&mut self, key: &K) -> CursorMut<'_, K> {
+        let mut position = Position::new(self.key_search);
+        position.seek(&self.root, key);
+        CursorMut { tree: self, position }
+    }
+}
+
+/// Read only cursor over the keys of a tree, see `BTree::cursor_at`.
+///
+/// The cursor sits on a key or on a "ghost" spot past the last key and before
+/// the first one, so stepping forward from the last key and then forward again
+/// wraps around to the first. It keeps the path from the root to its key, which
+/// makes a step O(1) amortized instead of a search from the root
+pub struct Cursor<'a, K> {
+    root: NodeRef<K>,
+    position: Position<K>,
+    tree: PhantomData<&'a BTree<K>>,
+}
+
+impl<K: Ord> Cursor<'_, K> {
+    /// A copy of the key under the cursor, `None` on the ghost spot
+    pub fn current(&self) -> Option<K>
+        where K: Clone {
+        self.position.current()
+    }
+
+    /// Step to the next key and return a copy of it
+    pub fn move_next(&mut self) -> Option<K>
+        where K: Clone {
+        self.position.move_next(&self.root);
+        self.current()
+    }
+
+    /// Step to the previous key and return a copy of it
+    pub fn move_prev(&mut self) -> Option<K>
+        where K: Clone {
+        self.position.move_prev(&self.root);
+        self.current()
+    }
+
+    /// Move to the first key at or after `key` with a search from the root
+    pub fn seek(&mut self, key: &K) {
+        self.position.seek(&self.root, key);
+    }
+
+    /// Move to the last key at or before `key`, or to the ghost spot if
+    /// every key is larger
+    pub fn seek_for_prev(&mut self, key: &K) {
+        self.position.seek_for_prev(&self.root, key);
+    }
+
+    /// Move to the smallest key, the ghost spot on an empty tree
+    pub fn seek_to_first(&mut self) {
+        self.position.seek_to_edge(&self.root, true);
+    }
+
+    /// Move to the largest key, the ghost spot on an empty tree
+    pub fn seek_to_last(&mut self) {
+        self.position.seek_to_edge(&self.root, false);
+    }
+}
+
+/// Cursor that edits the tree around its key, see `BTree::cursor_mut_at`.
+///
+/// Edits rebalance the tree like `add` and `delete` do and are passed on to
+/// subscribers and the changefeed. Edits start at the cursor's spot without a
+/// search, and the cursor is then placed again from the node its key ended up
+/// in, climbing the parent links, so it stays valid however the nodes were
+/// reshaped
+pub struct CursorMut<'a, K> {
+    tree: &'a mut BTree<K>,
+    position: Position<K>,
+}
+
+impl<'a, K: Ord + Clone> CursorMut<'a, K> {
+    /// A cursor on the smallest key
+    pub(crate) fn first(tree: &'a mut BTree<K>) -> Self {
+        let mut position = Position::new(tree.key_search);
+        position.move_next(&tree.root);
+        CursorMut { tree, position }
+    }
+}
+
+impl<K: Ord + Clone> CursorMut<'_, K> {
+    /// A copy of the key under the cursor, `None` on the ghost spot
+    pub fn current(&self) -> Option<K> {
+        self.position.current()
+    }
+
+    /// Step to the next key and return a copy of it
+    pub fn move_next(&mut self) -> Option<K> {
+        self.position.move_next(&self.tree.root);
+        self.current()
+    }
+
+    /// Step to the previous key and return a copy of it
+    pub fn move_prev(&mut self) -> Option<K> {
+        self.position.move_prev(&self.tree.root);
+        self.current()
+    }
+
+    /// Move to the first key at or after `key` with a search from the root
+    pub fn seek(&mut self, key: &K) {
+        self.position.seek(&self.tree.root, key);
+    }
+
+    /// Move to the last key at or before `key`, or to the ghost spot if
+    /// every key is larger
+    pub fn seek_for_prev(&mut self, key: &K) {
+        self.position.seek_for_prev(&self.tree.root, key);
+    }
+
+    /// Move to the smallest key, the ghost spot on an empty tree
+    pub fn seek_to_first(&mut self) {
+        self.position.seek_to_edge(&self.tree.root, true);
+    }
+
+    /// Move to the largest key, the ghost spot on an empty tree
+    pub fn seek_to_last(&mut self) {
+        self.position.seek_to_edge(&self.tree.root, false);
+    }
+
+    /// Remove the key under the cursor and move on to the next one.
+    /// Returns `None` without changing anything on the ghost spot
+    pub fn remove_current(&mut self) -> Option<K> {
+        let (node, idx) = self.position.path.last()?;
+        let (node, idx) = (Rc::clone(node), *idx);
+
+        let (key, next) = self.tree.remove_at(node, idx);
+        match next {
+            Some((node, idx)) => self.position.place(node, idx),
+            None => self.position.path.clear(),
+        }
+        self.tree.record_change(Change { key: key.clone(), kind: ChangeKind::Deleted });
+        Some(key)
+    }
+
+    /// Add `key` right before the key under the cursor, or after the last key
+    /// on the ghost spot. The cursor stays on its key.
+    ///
+    /// Fails with `UnorderedKey` if `key` doesn't sort between the current key
+    /// and the one before it
+    pub fn insert_before(&mut self, key: K) -> Result<(), BTreeError> {
+        let mut before = self.position.clone();
+        before.move_prev(&self.tree.root);
+        self.insert_between(&before, &self.position.clone(), key)?;
+        self.position.move_next(&self.tree.root);
+        Ok(())
+    }
+
+    /// Add `key` right after the key under the cursor, or before the first key
+    /// on the ghost spot. The cursor stays on its key.
+    ///
+    /// Fails with `UnorderedKey` if `key` doesn't sort between the current key
+    /// and the one after it
+    pub fn insert_after(&mut self, key: K) -> Result<(), BTreeError> {
+        let mut after = self.position.clone();
+        after.move_next(&self.tree.root);
+        self.insert_between(&self.position.clone(), &after, key)?;
+        self.position.move_prev(&self.tree.root);
+        Ok(())
+    }
+
+    /// Add `key` wherever it sorts, using the cursor's spot as a hint like
+    /// C++ `map::insert` does. The search only climbs the cursor's path as far
+    /// as the first node whose key range holds `key` and descends from there,
+    /// so a key close to the cursor skips most of the descent, while one far
+    /// from it costs a search from the root. The cursor moves to the new key,
+    /// ready to hint the next one.
+    ///
+    /// Fails with `ValueAlreadyExists` like `BTree::add`
+    pub fn insert_hint(&mut self, key: K) -> Result<(), BTreeError> {
+        let start = self.position.deepest_holding(&key);
+        self.position.path.truncate(start);
+        let mut node = match self.position.path.pop() {
+            Some((node, _)) => node,
+            None => Rc::clone(&self.tree.root),
+        };
+
+        let idx = loop {
+            let (status, child) = {
+                let node_ref = node.borrow();
+                let status = (self.tree.key_search)(&node_ref.keys, &key);
+                (status, status.err().and_then(|idx| node_ref.try_clone_child(idx as isize)))
+            };
+            match (status, child) {
+                (Ok(_), _) => {
+                    self.position.seek(&self.tree.root, &key);
+                    return Err(BTreeError::ValueAlreadyExists);
+                }
+                (Err(idx), Some(child)) => {
+                    self.position.path.push((node, idx));
+                    node = child;
+                }
+                (Err(idx), None) => break idx,
+            }
+        };
+
+        let node_count = self.tree.node_count;
+        match self.tree.insert_at(Rc::clone(&node), idx, key.clone()) {
+            // the path down to the leaf only holds if no node was split or
+            // took a key from its sibling
+            Ok((leaf, idx)) if node_count == self.tree.node_count && Rc::ptr_eq(&leaf, &node) =>
+                self.position.path.push((leaf, idx)),
+            Ok((node, idx)) => self.position.place(node, idx),
+            Err(err) => {
+                self.position.seek(&self.tree.root, &key);
+                return Err(err);
+            }
+        }
+        self.tree.record_change(Change { key, kind: ChangeKind::Inserted });
+        Ok(())
+    }
+
+    /// Call `f` with the key under the cursor, `None` on the ghost spot
+    pub(crate) fn current_with<R>(&self, f: impl FnOnce(&K) -> R) -> Option<R> {
+        self.position.current_with(f)
+    }
+
+    /// Step to the next key without copying it
+    pub(crate) fn advance(&mut self) {
+        self.position.move_next(&self.tree.root);
+    }
+
+    /// Add `key` between two neighboring spots, leaving the cursor on the new key
+    fn insert_between(
+        &mut self, before: &Position<K>, after: &Position<K>, key: K) -> Result<(), BTreeError> {
+        let below = before.current_with(|before| before.cmp(&key)).unwrap_or(Ordering::Less);
+        let above = after.current_with(|after| after.cmp(&key)).unwrap_or(Ordering::Greater);
+        if below == Ordering::Equal || above == Ordering::Equal {
+            return Err(BTreeError::ValueAlreadyExists);
+        }
+        if below == Ordering::Greater || above == Ordering::Less {
+            return Err(BTreeError::UnorderedKey);
+        }
+
+        // of two neighboring keys one is in a leaf, the new key goes next to it
+        let (leaf, idx) = match (after.path.last(), before.path.last()) {
+            (Some((node, idx)), _) if node.borrow().is_leaf() => (Rc::clone(node), *idx),
+            (_, Some((node, idx))) => (Rc::clone(node), idx + 1),
+            // both spots are the ghost spot of an empty tree
+            (None, None) => (Rc::clone(&self.tree.root), 0),
+            (Some(_), None) => unreachable!("an inner key always has a key before it"),
+        };
+
+        let (node, idx) = self.tree.insert_at(leaf, idx, key.clone())?;
+        self.position.place(node, idx);
+        self.tree.record_change(Change { key, kind: ChangeKind::Inserted });
+        Ok(())
+    }
+}
+
+/// A spot in the tree shared by the cursors: the nodes from the root down to
+/// the current key. Every node but the last is paired with the index of the
+/// child taken, the last one with the index of the current key. An empty path
+/// is the ghost spot
+#[derive(Clone)]
+pub(crate) struct Position<K> {
+    path: Vec<(NodeRef<K>, usize)>,
+    key_search: KeySearch<K>,
+}
+
+impl<K: Ord> Position<K> {
+    pub(crate) fn new(key_search: KeySearch<K>) -> Self {
+        Position { path: Vec::new(), key_search }
+    }
+
+    pub(crate) fn current(&self) -> Option<K>
+        where K: Clone {
+        self.current_with(K::clone)
+    }
+
+    pub(crate) fn current_with<R>(&self, f: impl FnOnce(&K) -> R) -> Option<R> {
+        let (node, idx) = self.path.last()?;
+        let node = node.borrow();
+        Some(f(&node.keys[*idx]))
+    }
+
+    pub(crate) fn seek(&mut self, root: &NodeRef<K>, key: &K) {
+        self.path.clear();
+        let mut node = Rc::clone(root);
+
+        loop {
+            let (status, child) = {
+                let node_ref = node.borrow();
+                let status = (self.key_search)(&node_ref.keys, key);
+                let child = match status {
+                    Ok(_) => None,
+                    Err(idx) => node_ref.try_clone_child(idx as isize),
+                };
+                (status, child)
+            };
+
+            match (status, child) {
+                (Ok(idx), _) => return self.path.push((node, idx)),
+                (Err(idx), Some(child)) => {
+                    self.path.push((node, idx));
+                    node = child;
+                }
+                (Err(idx), None) => {
+                    let len = node.borrow().keys.len();
+                    if len == 0 {
+                        // only an empty root has no keys
+                        return self.path.clear();
+                    }
+                    if idx < len {
+                        return self.path.push((node, idx));
+                    }
+                    // every key of the leaf is smaller, the next one is an ancestor's
+                    self.path.push((node, len - 1));
+                    return self.step_up_forward();
+                }
+            }
+        }
+    }
+
+    /// Length of the path down to the deepest node whose key range holds
+    /// `key`, found by climbing from the current spot until the keys around
+    /// the children taken bound `key` on both sides. 0 when the path is empty
+    fn deepest_holding(&self, key: &K) -> usize {
+        let mut holding = self.path.len();
+        let (mut lower, mut upper) = (false, false);
+
+        for (depth, (node, child_idx)) in self.path.iter().enumerate().rev().skip(1) {
+            if lower && upper {
+                break;
+            }
+            let node = node.borrow();
+            let below = *child_idx > 0 && node.keys[child_idx - 1] < *key;
+            let above = *child_idx < node.keys.len() && *key < node.keys[*child_idx];
+            let lower_out = !lower && *child_idx > 0 && !below;
+            let upper_out = !upper && *child_idx < node.keys.len() && !above;
+
+            if lower_out || upper_out {
+                // the child taken can't hold `key`, but this node may
+                holding = depth + 1;
+                (lower, upper) = (false, false);
+            } else {
+                lower |= below;
+                upper |= above;
+            }
+        }
+        holding
+    }
+
+    /// Point at the key at `idx` of `node`, rebuilding the path from the
+    /// node's parent links instead of searching from the root
+    pub(crate) fn place(&mut self, node: NodeRef<K>, idx: usize) {
+        self.path.clear();
+        let (mut node, mut idx) = (node, idx);
+        loop {
+            let parent = {
+                let node_ref = node.borrow();
+                node_ref.parent.upgrade().zip(node_ref.index_in_parent)
+            };
+            self.path.push((node, idx));
+            match parent {
+                Some((parent, child_idx)) => (node, idx) = (parent, child_idx),
+                None => break,
+            }
+        }
+        self.path.reverse();
+    }
+
+    pub(crate) fn seek_for_prev(&mut self, root: &NodeRef<K>, key: &K) {
+        self.seek(root, key);
+        if self.current_with(|current| current != key).unwrap_or(true) {
+            // past `key` or on the ghost spot, the key before is the one wanted
+            self.move_prev(root);
+        }
+    }
+
+    pub(crate) fn seek_to_edge(&mut self, root: &NodeRef<K>, first: bool) {
+        self.path.clear();
+        self.descend(Rc::clone(root), first);
+    }
+
+    pub(crate) fn move_next(&mut self, root: &NodeRef<K>) {
+        let (node, idx) = match self.path.last() {
+            Some((node, idx)) => (Rc::clone(node), *idx),
+            None => return self.descend(Rc::clone(root), true),
+        };
+
+        let child = node.borrow().try_clone_child(idx as isize + 1);
+        match child {
+            Some(child) => {
+                self.path.last_mut().unwrap().1 = idx + 1;
+                self.descend(child, true);
+            }
+            None if idx + 1 < node.borrow().keys.len() => self.path.last_mut().unwrap().1 += 1,
+            None => self.step_up_forward(),
+        }
+    }
+
+    pub(crate) fn move_prev(&mut self, root: &NodeRef<K>) {
+        let (node, idx) = match self.path.last() {
+            Some((node, idx)) => (Rc::clone(node), *idx),
+            None => return self.descend(Rc::clone(root), false),
+        };
+
+        let child = node.borrow().try_clone_child(idx as isize);
+        match child {
+            Some(child) => self.descend(child, false),
+            None if idx > 0 => self.path.last_mut().unwrap().1 -= 1,
+            None => self.step_up_backward(),
+        }
+    }
+
+    /// Follow the first or last children from `node` down to a leaf and stop on
+    /// its first or last key
+    fn descend(&mut self, node: NodeRef<K>, first: bool) {
+        let mut node = node;
+        loop {
+            let (child_idx, child) = {
+                let node_ref = node.borrow();
+                let child_idx = if first { 0 } else { node_ref.keys.len() };
+                (child_idx, node_ref.try_clone_child(child_idx as isize))
+            };
+
+            match child {
+                Some(child) => {
+                    self.path.push((node, child_idx));
+                    node = child;
+                }
+                None => {
+                    let len = node.borrow().keys.len();
+                    if len == 0 {
+                        return self.path.clear();
+                    }
+                    return self.path.push((node, if first { 0 } else { len - 1 }));
+                }
+            }
+        }
+    }
+
+    /// Leave a leaf past its last key for the first ancestor with a key to the
+    /// right of the child taken
+    fn step_up_forward(&mut self) {
+        self.path.pop();
+        while let Some((node, child_idx)) = self.path.last() {
+            if *child_idx < node.borrow().keys.len() {
+                return;
+            }
+            self.path.pop();
+        }
+    }
+
+    /// Leave a leaf before its first key for the first ancestor with a key to
+    /// the left of the child taken
+    fn step_up_backward(&mut self) {
+        self.path.pop();
+        while let Some((_, child_idx)) = self.path.last_mut() {
+            if *child_idx > 0 {
+                *child_idx -= 1;
+                return;
+            }
+            self.path.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CursorMut, Position};
+    use crate::{BTree, BTreeError, ChangeKind};
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::rc::Rc;
+
+    fn build_tree() -> BTree<usize> {
+        let mut tree = BTree::new(3);
+        for key in (0..100).map(|k| k * 37 % 101) {
+            let _ = tree.add(key * 2);
+        }
+        tree
+    }
+
+    #[test]
+    fn walks_every_key_both_ways() {
+        let tree = build_tree();
+        let keys: Vec<usize> = tree.iter().collect();
+
+        let mut cursor = tree.cursor_at(&0);
+        let mut forward = vec![cursor.current().unwrap()];
+        while let Some(key) = cursor.move_next() {
+            forward.push(key);
+        }
+        assert_eq!(forward, keys);
+
+        let mut backward = Vec::new();
+        while let Some(key) = cursor.move_prev() {
+            backward.push(key);
+        }
+        backward.reverse();
+        assert_eq!(backward, keys);
+    }
+
+    #[test]
+    fn lands_at_or_after_the_key() {
+        let tree = build_tree();
+        assert_eq!(tree.cursor_at(&40).current(), Some(40));
+        assert_eq!(tree.cursor_at(&41).current(), Some(42));
+        // 128 is the one even number missing
+        assert_eq!(tree.cursor_at(&127).current(), Some(130));
+        assert_eq!(tree.cursor_at(&201).current(), None);
+
+        let mut cursor = tree.cursor_at(&201);
+        assert_eq!(cursor.move_prev(), Some(200));
+        assert_eq!(cursor.move_next(), None);
+        assert_eq!(cursor.move_next(), Some(0));
+
+        cursor.seek(&127);
+        assert_eq!(cursor.move_prev(), Some(126));
+        assert_eq!(cursor.move_next(), Some(130));
+    }
+
+    #[test]
+    fn seeks_to_the_edges_and_backwards() {
+        let tree = build_tree();
+        let mut cursor = tree.cursor_at(&100);
+        cursor.seek_to_first();
+        assert_eq!(cursor.current(), Some(0));
+        cursor.seek_to_last();
+        assert_eq!(cursor.current(), Some(200));
+
+        cursor.seek_for_prev(&40);
+        assert_eq!(cursor.current(), Some(40));
+        cursor.seek_for_prev(&129);
+        assert_eq!(cursor.current(), Some(126));
+        cursor.seek_for_prev(&1000);
+        assert_eq!(cursor.current(), Some(200));
+
+        let mut tree = BTree::new(4);
+        tree.extend(5..10);
+        let mut cursor = tree.cursor_mut_at(&7);
+        cursor.seek_for_prev(&4);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.move_next(), Some(5));
+        cursor.seek_to_last();
+        assert_eq!(cursor.remove_current(), Some(9));
+
+        let mut empty = BTree::<usize>::new(3);
+        let mut cursor = empty.cursor_mut_at(&0);
+        cursor.seek_to_first();
+        cursor.seek_for_prev(&3);
+        assert_eq!(cursor.current(), None);
+    }
+
+    #[test]
+    fn empty_tree_cursor_stays_on_the_ghost() {
+        let tree = BTree::<usize>::new(3);
+        let mut cursor = tree.cursor_at(&1);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.move_next(), None);
+        assert_eq!(cursor.move_prev(), None);
+    }
+
+    #[test]
+    fn scan_and_remove() {
+        let mut tree = BTree::builder(3).check_invariants().build();
+        for key in 0..200 {
+            let _ = tree.add(key);
+        }
+
+        let mut cursor = tree.cursor_mut_at(&50);
+        while let Some(key) = cursor.current() {
+            if key % 3 == 0 {
+                assert_eq!(cursor.remove_current(), Some(key));
+            } else {
+                cursor.move_next();
+            }
+        }
+        assert_eq!(cursor.remove_current(), None);
+        assert_eq!(cursor.move_prev(), Some(199));
+
+        let expected: Vec<usize> = (0..200).filter(|k| *k < 50 || k % 3 != 0).collect();
+        assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(tree.len(), expected.len());
+    }
+
+    #[test]
+    fn inserts_keep_the_cursor_on_its_key() {
+        let mut tree = BTree::builder(3).check_invariants().changefeed().build();
+        for key in (0..20).map(|k| k * 10) {
+            let _ = tree.add(key);
+        }
+
+        let mut cursor = tree.cursor_mut_at(&100);
+        for offset in 1..10 {
+            assert!(cursor.insert_before(90 + offset).is_ok());
+            assert!(cursor.insert_after(110 - offset).is_ok());
+            assert_eq!(cursor.current(), Some(100));
+        }
+        assert!(matches!(cursor.insert_before(99), Err(BTreeError::ValueAlreadyExists)));
+        assert!(matches!(cursor.insert_after(111), Err(BTreeError::UnorderedKey)));
+        assert!(matches!(cursor.insert_before(50), Err(BTreeError::UnorderedKey)));
+
+        // on the ghost spot the neighbors are the last and the first keys
+        cursor.seek(&1000);
+        assert!(cursor.insert_before(1000).is_ok());
+        assert!(cursor.insert_after(5).is_err());
+        assert_eq!(cursor.move_next(), Some(0));
+
+        assert_eq!(tree.iter().filter(|k| (90..=110).contains(k)).count(), 21);
+        assert_eq!(tree.last(), Some(1000));
+        let last = tree.changes_since(0).unwrap().last().unwrap();
+        assert_eq!((last.change.key, last.change.kind), (1000, ChangeKind::Inserted));
+    }
+
+    #[test]
+    fn hinted_inserts_land_anywhere() {
+        let mut tree = BTree::builder(3).check_invariants().changefeed().build();
+        tree.extend((0..50).map(|k| k * 10));
+
+        let mut cursor = tree.cursor_mut_at(&200);
+        for key in (201..210).chain((191..200).rev()) {
+            assert!(cursor.insert_hint(key).is_ok());
+            assert_eq!(cursor.current(), Some(key));
+        }
+        // far from the hint, before the first key and on the ghost spot
+        assert!(cursor.insert_hint(5).is_ok());
+        assert_eq!(cursor.move_prev(), Some(0));
+        cursor.seek(&1000);
+        assert!(cursor.insert_hint(495).is_ok());
+        assert!(cursor.insert_hint(1000).is_ok());
+        assert!(matches!(cursor.insert_hint(205), Err(BTreeError::ValueAlreadyExists)));
+        assert_eq!(cursor.current(), Some(205));
+
+        let mut empty = BTree::builder(4).check_invariants().build();
+        assert!(empty.cursor_mut_at(&0).insert_hint(7).is_ok());
+        assert_eq!(empty.iter().collect::<Vec<_>>(), vec![7]);
+
+        let mut expected: Vec<usize> = (0..50).map(|k| k * 10).chain(191..210).collect();
+        expected.extend([5, 495, 1000]);
+        expected.sort();
+        expected.dedup();
+        assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(tree.changes_since(50).unwrap().count(), 21);
+    }
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// A key counting how often it is compared
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Counted(usize);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Self) -> Ordering {
+            COMPARISONS.with(|count| count.set(count.get() + 1));
+            self.0.cmp(&other.0)
+        }
+    }
+
+    /// Whether the cursor's path is the one a search from the root would take
+    fn placed_like_a_seek<K: Ord + Clone>(cursor: &CursorMut<'_, K>) -> bool {
+        let mut seeked = Position::new(cursor.tree.key_search);
+        if let Some(key) = cursor.current() {
+            seeked.seek(&cursor.tree.root, &key);
+        }
+        let path = |position: &Position<K>| position.path.iter()
+            .map(|(node, idx)| (Rc::as_ptr(node), *idx))
+            .collect::<Vec<_>>();
+        path(&cursor.position) == path(&seeked)
+    }
+
+    #[test]
+    fn edits_place_the_cursor_without_searching() {
+        let mut tree = BTree::new(3);
+        tree.extend((0..500).map(|k| Counted(k * 2)));
+        let mut cursor = tree.cursor_mut_at(&Counted(300));
+
+        for key in 0..200 {
+            COMPARISONS.with(|count| count.set(0));
+            let before = cursor.current().unwrap().0 - 1;
+            assert!(cursor.insert_before(Counted(before)).is_ok());
+            // against the keys on either side and nothing else
+            assert_eq!(COMPARISONS.with(Cell::get), 2);
+            assert!(placed_like_a_seek(&cursor));
+
+            COMPARISONS.with(|count| count.set(0));
+            let removed = if key % 3 == 0 { cursor.move_prev() } else { cursor.current() };
+            assert_eq!(cursor.remove_current(), removed);
+            assert_eq!(COMPARISONS.with(Cell::get), 0);
+            assert!(placed_like_a_seek(&cursor));
+            if key % 5 == 0 {
+                cursor.move_prev();
+            }
+        }
+        assert!(tree.inspect().is_valid());
+        assert_eq!(tree.len(), 500);
+    }
+}
@@ -1,29 +1,43 @@
-use std::cell::RefMut;
-use crate::Node;
+use std::mem;
+use std::rc::Rc;
+use crate::node::node_utils::edge_leaf;
+use crate::NodeRef;
 
-pub(super) fn delete_inner(deleted_key_node: &mut RefMut<Node>, deleted_key_index: usize) {
+/// The logic to delete a key from an inner node
+///
+/// The key is replaced by its in-order predecessor, the largest key of the left
+/// subtree, or by its successor, the smallest key of the right subtree. Both sit
+/// in a leaf, so the node keeps its children. The successor is only used when
+/// its leaf can spare a key and the predecessor's leaf can't, otherwise the
+/// predecessor's leaf is left under the minimum to be merged with a sibling
+///
+/// # Returns
+/// (deleted_key, leaf, next) => The key removed from the node, the leaf that gave
+/// up its replacement and the node and index of the key that followed the
+/// deleted one
+pub(super) fn delete_inner<K: Ord>(
+   deleted_key_node: &NodeRef<K>, deleted_key_index: usize) -> (K, NodeRef<K>, (NodeRef<K>, usize)) {
+   let (left_child, right_child) = {
+      let node = deleted_key_node.borrow();
+      (Rc::clone(&node.children[deleted_key_index]),
+         Rc::clone(&node.children[deleted_key_index + 1]))
+   };
 
-   let left_child_ref = deleted_key_node
-         .try_clone_child(deleted_key_index as isize - 1);
+   let predecessor_leaf = edge_leaf(left_child, false);
+   let successor_leaf = edge_leaf(right_child, true);
 
-   match left_child_ref {
-      Some(left_child) if left_child.borrow_mut().has_more_than_min_keys() => {
-         let mut left_child = left_child.borrow_mut();
-         let child_key = left_child.keys.pop().unwrap();
-         deleted_key_node.add_key(child_key);
-      },
-      _ => ()
-   }
+   let use_successor = !predecessor_leaf.borrow().has_more_than_min_keys()
+      && successor_leaf.borrow().has_more_than_min_keys();
 
-   let right_child_ref = deleted_key_node
-      .try_clone_child(deleted_key_index as isize);
+   let (replacement, leaf, next) = if use_successor {
+      let key = successor_leaf.borrow_mut().keys.remove(0);
+      (key, successor_leaf, (Rc::clone(deleted_key_node), deleted_key_index))
+   } else {
+      let key = predecessor_leaf.borrow_mut().keys.pop().unwrap();
+      (key, predecessor_leaf, (successor_leaf, 0))
+   };
 
-   match right_child_ref {
-      Some(right_child) if right_child.borrow_mut().has_more_than_min_keys() => {
-         let mut left_child = right_child.borrow_mut();
-         let child_key = left_child.keys.pop().unwrap();
-         deleted_key_node.add_key(child_key);
-      },
-      _ => ()
-   }
-}
\ No newline at end of file
+   let deleted_key = mem::replace(
+      &mut deleted_key_node.borrow_mut().keys[deleted_key_index], replacement);
+   (deleted_key, leaf, next)
+}
@@ -0,0 +1,87 @@
+use crate::node::NodeRef;
+use crate::BTree;
+use std::fmt::{Display, Write};
+
+impl<K: Display> BTree<K> {
+    /// Describe the tree as a Graphviz digraph, render it with e.g.
+    /// `dot -Tsvg tree.dot -o tree.svg`. Each node is a record of its keys with a
+    /// port between every two keys that the edge to the matching child starts at
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph BTree {\n    node [shape=record];\n");
+        let mut next_id = 0;
+        write_node(&mut out, &self.root, &mut next_id);
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Write `node` and its subtree, numbering the nodes in pre-order
+fn write_node<K: Display>(out: &mut String, node: &NodeRef<K>, next_id: &mut usize) {
+    let node = node.borrow();
+    let id = *next_id;
+    *next_id += 1;
+
+    let keys = node.keys.iter().map(|key| escape(&key.to_string()));
+    let mut fields: Vec<String> = Vec::new();
+    for (idx, key) in keys.enumerate() {
+        if !node.children.is_empty() {
+            fields.push(format!("<c{}> ", idx));
+        }
+        fields.push(key);
+    }
+    if !node.children.is_empty() {
+        fields.push(format!("<c{}> ", node.keys.len()));
+    }
+    let _ = writeln!(out, "    n{} [label=\"{}\"];", id, fields.join("|"));
+
+    for (idx, child) in node.children.iter().enumerate() {
+        let _ = writeln!(out, "    n{}:c{} -> n{};", id, idx, *next_id);
+        write_node(out, child, next_id);
+    }
+}
+
+/// Escape the characters that have a meaning inside a record label
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\' | ' ') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+
+    #[test]
+    fn records_and_edges() {
+        let mut tree = BTree::new(3);
+        for key in [10, 20, 30] {
+            let _ = tree.add(key);
+        }
+
+        assert_eq!(tree.to_dot(), "\
+digraph BTree {
+    node [shape=record];
+    n0 [label=\"<c0> |20|<c1> \"];
+    n0:c0 -> n1;
+    n1 [label=\"10\"];
+    n0:c1 -> n2;
+    n2 [label=\"30\"];
+}
+");
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let mut tree = BTree::new(3);
+        let _ = tree.add(String::from("a|b"));
+        let _ = tree.add(String::from("{c}"));
+
+        assert!(tree.to_dot().contains(r#"n0 [label="a\|b|\{c\}"];"#));
+        assert!(BTree::<usize>::new(3).to_dot().contains("n0 [label=\"\"];"));
+    }
+}
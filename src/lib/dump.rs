@@ -0,0 +1,201 @@
+use crate::{BTree, BinaryKey};
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::str::FromStr;
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Write every key in ascending order, one key per line. Backslashes and
+    /// line breaks inside a key are escaped as `\\`, `\n` and `\r`, so every
+    /// key stays on its own line and reads back exactly
+    pub fn dump_text<W: Write>(&self, mut writer: W) -> io::Result<()>
+        where K: Display {
+        self.root.borrow().try_for_each_key(&mut |key| writeln!(writer, "{}", escape(&key.to_string())))
+    }
+
+    /// Build a tree of the given order from a text dump written by `dump_text`.
+    /// Every line, blank or not, is unescaped and parsed as it is
+    pub fn load_text<R: Read>(order: usize, reader: R) -> io::Result<BTree<K>>
+        where K: FromStr, K::Err: Into<Box<dyn std::error::Error + Send + Sync>> {
+        let mut tree = BTree::new(order);
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let key = unescape(&line)?.parse::<K>().map_err(invalid_data)?;
+            tree.add(key).map_err(|err| invalid_data(format!("{:?}: {}", err, line)))?;
+        }
+
+        Ok(tree)
+    }
+
+    /// Write the key count as a little endian `u64` followed by every key in
+    /// ascending order, encoded with `BinaryKey`
+    pub fn dump_binary<W: Write>(&self, mut writer: W) -> io::Result<()>
+        where K: BinaryKey {
+        (self.len() as u64).write_key(&mut writer)?;
+        self.root.borrow().try_for_each_key(&mut |key| key.write_key(&mut writer))
+    }
+
+    /// Build a tree of the given order from a dump written by `dump_binary`
+    pub fn load_binary<R: Read>(order: usize, mut reader: R) -> io::Result<BTree<K>>
+        where K: BinaryKey {
+        let mut tree = BTree::new(order);
+        let count = u64::read_key(&mut reader)?;
+
+        for idx in 0..count {
+            let key = K::read_key(&mut reader)?;
+            tree.add(key).map_err(|err| invalid_data(format!("{:?}: key number {}", err, idx)))?;
+        }
+
+        Ok(tree)
+    }
+}
+
+/// Escape what would end a line or be read as an escape, see `dump_text`
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Undo `escape`, failing on a backslash not followed by `\`, `n` or `r`
+fn unescape(line: &str) -> io::Result<String> {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => text.push('\\'),
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            _ => return Err(invalid_data(format!("bad escape: {}", line))),
+        }
+    }
+    Ok(text)
+}
+
+pub(crate) fn invalid_data<E>(err: E) -> io::Error
+    where E: Into<Box<dyn std::error::Error + Send + Sync>> {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+    use std::io::ErrorKind;
+
+    fn build_tree() -> BTree<usize> {
+        let mut tree = BTree::new(3);
+        for key in [40, 10, 30, 20, 0, 50, 60] {
+            let _ = tree.add(key);
+        }
+        tree
+    }
+
+    fn collect_keys<K: Ord + Clone>(tree: &BTree<K>) -> Vec<K> {
+        let mut keys = Vec::new();
+        let _ = tree.root.borrow().try_for_each_key(&mut |key| -> Result<(), ()> {
+            keys.push(key.clone());
+            Ok(())
+        });
+        keys
+    }
+
+    #[test]
+    fn text_dump_is_one_key_per_line() {
+        let tree = build_tree();
+        let mut out = Vec::new();
+        tree.dump_text(&mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "0\n10\n20\n30\n40\n50\n60\n");
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let tree = build_tree();
+        let mut out = Vec::new();
+        tree.dump_text(&mut out).unwrap();
+
+        let loaded = BTree::<usize>::load_text(4, out.as_slice()).unwrap();
+        assert_eq!(collect_keys(&loaded), vec![0, 10, 20, 30, 40, 50, 60]);
+    }
+
+    #[test]
+    fn text_load_rejects_bad_lines() {
+        let err = BTree::<usize>::load_text(3, "1\n\nabc\n".as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = BTree::<usize>::load_text(3, "1\n1\n".as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = BTree::<String>::load_text(3, "a\\tb\n".as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn text_keeps_spaces_and_line_breaks() {
+        let keys = ["", " a ", "a\nb", "a\\nb", "c\r", "\\"];
+        let mut tree = BTree::new(3);
+        for key in keys {
+            tree.add(key.to_string()).unwrap();
+        }
+
+        let mut out = Vec::new();
+        tree.dump_text(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "\n a \n\\\\\na\\nb\na\\\\nb\nc\\r\n");
+
+        let loaded = BTree::<String>::load_text(3, out.as_slice()).unwrap();
+        assert_eq!(collect_keys(&loaded), collect_keys(&tree));
+        assert_eq!(loaded.len(), keys.len());
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let tree = build_tree();
+        let mut out = Vec::new();
+        tree.dump_binary(&mut out).unwrap();
+
+        assert_eq!(out.len(), 8 * 8);
+        assert_eq!(out[..8], 7_u64.to_le_bytes());
+
+        let loaded = BTree::<usize>::load_binary(3, out.as_slice()).unwrap();
+        assert_eq!(collect_keys(&loaded), vec![0, 10, 20, 30, 40, 50, 60]);
+    }
+
+    #[test]
+    fn binary_load_rejects_truncated_input() {
+        let tree = build_tree();
+        let mut out = Vec::new();
+        tree.dump_binary(&mut out).unwrap();
+        out.truncate(out.len() - 3);
+
+        let err = BTree::<usize>::load_binary(3, out.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn string_keys_round_trip() {
+        let mut tree = BTree::new(3);
+        for key in ["pear", "apple", "fig", "kiwi"] {
+            let _ = tree.add(key.to_string());
+        }
+
+        let mut text = Vec::new();
+        tree.dump_text(&mut text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "apple\nfig\nkiwi\npear\n");
+
+        let mut binary = Vec::new();
+        tree.dump_binary(&mut binary).unwrap();
+        let loaded = BTree::<String>::load_binary(3, binary.as_slice()).unwrap();
+        assert_eq!(collect_keys(&loaded), vec!["apple", "fig", "kiwi", "pear"]);
+    }
+}
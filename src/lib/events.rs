@@ -0,0 +1,107 @@
+use crate::node::NodeRef;
+use crate::BTree;
+use std::mem;
+use std::rc::Rc;
+
+/// An internal step of an operation, see `BTree::record_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeStep {
+    /// A key was put into a leaf, which may now hold one key too many
+    KeyAdded,
+    /// A key was taken out of a leaf, which may now hold too few
+    KeyRemoved,
+    /// A full node was split in two around its middle key, which moved up.
+    /// A split of the root adds a level
+    Split,
+    /// A node under the minimum was merged with a sibling
+    Merged,
+    /// A key was rotated through the parent into a node under the minimum,
+    /// or out of a full node on a packed append
+    Rotated,
+    /// The emptied root was replaced by its only child, removing a level
+    RootCollapsed,
+}
+
+/// A step and the keys of every node right after it, grouped by level from
+/// the root and ordered left to right within a level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEvent<K> {
+    pub step: TreeStep,
+    pub levels: Vec<Vec<Vec<K>>>,
+}
+
+/// The events recorded so far, with the function taking the snapshots
+pub(crate) struct EventLog<K> {
+    events: Vec<TreeEvent<K>>,
+    keys_by_level: fn(&BTree<K>) -> Vec<Vec<Vec<K>>>,
+}
+
+impl<K> BTree<K> {
+    /// Start or stop recording a `TreeEvent` after every internal step of an
+    /// insert or delete, for stepping through an operation or animating it.
+    /// Each event copies every key of the tree, so this is meant for small
+    /// trees. Bulk rebuilds like `retain` and `compact` record no steps
+    pub fn record_events(&mut self, record: bool)
+        where K: Clone {
+        self.events = record.then(|| EventLog { events: Vec::new(), keys_by_level });
+    }
+
+    /// The events recorded since recording started or the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<TreeEvent<K>> {
+        self.events.as_mut().map(|log| mem::take(&mut log.events)).unwrap_or_default()
+    }
+
+    pub(crate) fn record_event(&mut self, step: TreeStep) {
+        let Some(keys_by_level) = self.events.as_ref().map(|log| log.keys_by_level) else {
+            return;
+        };
+        let levels = keys_by_level(self);
+        if let Some(log) = self.events.as_mut() {
+            log.events.push(TreeEvent { step, levels });
+        }
+    }
+}
+
+fn keys_by_level<K: Clone>(tree: &BTree<K>) -> Vec<Vec<Vec<K>>> {
+    let mut levels = Vec::new();
+    let mut level: Vec<NodeRef<K>> = vec![Rc::clone(&tree.root)];
+
+    while !level.is_empty() {
+        levels.push(level.iter().map(|node| node.borrow().keys.clone()).collect());
+        level = level.iter()
+            .flat_map(|node| node.borrow().children.clone())
+            .collect();
+    }
+
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_step_is_recorded() {
+        let mut tree = BTree::new(3);
+        tree.extend([1, 2]);
+        tree.record_events(true);
+
+        let _ = tree.add(3);
+        let _ = tree.delete(&1);
+        let steps: Vec<(TreeStep, Vec<Vec<Vec<u32>>>)> = tree.take_events().into_iter()
+            .map(|event| (event.step, event.levels))
+            .collect();
+        assert_eq!(steps, vec![
+            (TreeStep::KeyAdded, vec![vec![vec![1, 2, 3]]]),
+            (TreeStep::Split, vec![vec![vec![2]], vec![vec![1], vec![3]]]),
+            (TreeStep::KeyRemoved, vec![vec![vec![2]], vec![vec![], vec![3]]]),
+            (TreeStep::Merged, vec![vec![vec![]], vec![vec![2, 3]]]),
+            (TreeStep::RootCollapsed, vec![vec![vec![2, 3]]]),
+        ]);
+        assert!(tree.take_events().is_empty());
+
+        tree.record_events(false);
+        let _ = tree.add(4);
+        assert!(tree.take_events().is_empty());
+    }
+}
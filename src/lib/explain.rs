@@ -0,0 +1,96 @@
+use crate::node::search_status::SearchStatus;
+use crate::BTree;
+use std::fmt;
+use std::rc::Rc;
+
+/// A node a search went through, see `BTree::explain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisitedNode<K> {
+    pub keys: Vec<K>,
+    /// Index of the child the search went down to, `None` on the node it ended in
+    pub child_taken: Option<usize>,
+}
+
+/// The way down a search took for a key, returned by `BTree::explain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchReport<K> {
+    /// Every node visited, from the root to the node the search ended in
+    pub visited: Vec<VisitedNode<K>>,
+    /// The outcome in the last node visited, the key's index in it or the
+    /// index it would be inserted at
+    pub status: SearchStatus,
+}
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Search for `key` the way `contains` does, with the tree's search mode,
+    /// and report each node visited and the child taken below it. Copies the
+    /// keys of every visited node, so it is meant for debugging
+    pub fn explain(&self, key: &K) -> SearchReport<K> {
+        let key_search = self.key_search;
+        let mut visited = Vec::new();
+        let mut node = Rc::clone(&self.root);
+
+        loop {
+            let (status, child) = {
+                let node_ref = node.borrow();
+                let status = node_ref.search_keys(|keys| key_search(keys, key));
+                let child = match status {
+                    SearchStatus::Found(_) => None,
+                    SearchStatus::NotFound(idx) => node_ref.children.get(idx).cloned(),
+                };
+                visited.push(VisitedNode {
+                    keys: node_ref.keys.clone(),
+                    child_taken: child.as_ref().map(|_| status.unwrap()),
+                });
+                (status, child)
+            };
+
+            match child {
+                Some(child) => node = child,
+                None => return SearchReport { visited, status },
+            }
+        }
+    }
+}
+
+/// One line per visited node:
+/// ```text
+/// [4] -> child 1
+/// [6] -> child 0
+/// [5] -> found at 0
+/// ```
+impl<K: fmt::Debug> fmt::Display for SearchReport<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in self.visited.iter() {
+            write!(f, "{:?} -> ", node.keys)?;
+            match (node.child_taken, &self.status) {
+                (Some(child), _) => writeln!(f, "child {}", child)?,
+                (None, SearchStatus::Found(idx)) => writeln!(f, "found at {}", idx)?,
+                (None, SearchStatus::NotFound(idx)) => writeln!(f, "not found, would go at {}", idx)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_path_is_reported() {
+        // [4] over [2] [6] over [1] [3] [5] [7]
+        let mut tree = BTree::new(3);
+        tree.extend(1..=7_u32);
+
+        let report = tree.explain(&5);
+        assert_eq!(report.status, SearchStatus::Found(0));
+        assert_eq!(report.to_string(), "[4] -> child 1\n[6] -> child 0\n[5] -> found at 0\n");
+
+        let report = tree.explain(&10);
+        assert_eq!(report.status, SearchStatus::NotFound(1));
+        assert_eq!(report.visited.last(), Some(&VisitedNode { keys: vec![7], child_taken: None }));
+        assert_eq!(tree.explain(&4).visited.len(), 1);
+        assert_eq!(BTree::<u32>::new(3).explain(&1).to_string(), "[] -> not found, would go at 0\n");
+    }
+}
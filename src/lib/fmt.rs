@@ -0,0 +1,132 @@
+use crate::node::NodeRef;
+use crate::BTree;
+use std::fmt;
+use std::rc::Rc;
+
+/// Shows the keys of every node level by level, starting at the root and going
+/// left to right within a level. Parent links are left out
+impl<K: fmt::Debug> fmt::Debug for BTree<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BTree")
+            .field("order", &self.order)
+            .field("len", &self.key_count)
+            .field("levels", &Levels(&self.root))
+            .finish()
+    }
+}
+
+/// Formats each level as a list of the nodes' key lists
+struct Levels<'a, K>(&'a NodeRef<K>);
+
+impl<K: fmt::Debug> fmt::Debug for Levels<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        let mut level: Vec<NodeRef<K>> = vec![Rc::clone(self.0)];
+
+        while !level.is_empty() {
+            list.entry(&Level(&level));
+            level = level.iter()
+                .flat_map(|node| node.borrow().children.clone())
+                .collect();
+        }
+
+        list.finish()
+    }
+}
+
+struct Level<'a, K>(&'a [NodeRef<K>]);
+
+impl<K: fmt::Debug> fmt::Debug for Level<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        for node in self.0 {
+            list.entry(&node.borrow().keys);
+        }
+        list.finish()
+    }
+}
+
+/// Draws the tree with one node per line, each child indented under its parent:
+/// ```text
+/// [4]
+/// ├── [2]
+/// │   ├── [1]
+/// │   └── [3]
+/// └── [6]
+///     ├── [5]
+///     └── [7]
+/// ```
+impl<K: fmt::Display> fmt::Display for BTree<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_keys(f, &self.root)?;
+        writeln!(f)?;
+        write_children(f, &self.root, "")
+    }
+}
+
+fn write_keys<K: fmt::Display>(f: &mut fmt::Formatter<'_>, node: &NodeRef<K>) -> fmt::Result {
+    write!(f, "[")?;
+    for (idx, key) in node.borrow().keys.iter().enumerate() {
+        if idx > 0 { write!(f, ", ")?; }
+        write!(f, "{}", key)?;
+    }
+    write!(f, "]")
+}
+
+/// Write a line for every child of `node`, starting each with `prefix`
+fn write_children<K: fmt::Display>(
+    f: &mut fmt::Formatter<'_>, node: &NodeRef<K>, prefix: &str) -> fmt::Result {
+    let node = node.borrow();
+
+    for (idx, child) in node.children.iter().enumerate() {
+        let is_last = idx + 1 == node.children.len();
+        let (branch, indent) = if is_last { ("└── ", "    ") } else { ("├── ", "│   ") };
+
+        write!(f, "{}{}", prefix, branch)?;
+        write_keys(f, child)?;
+        writeln!(f)?;
+        write_children(f, child, &format!("{}{}", prefix, indent))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+
+    #[test]
+    fn debug_lists_keys_by_level() {
+        let mut tree = BTree::new(3);
+        for key in 1..=7 {
+            let _ = tree.add(key);
+        }
+
+        assert_eq!(
+            format!("{:?}", tree),
+            "BTree { order: 3, len: 7, levels: [[[4]], [[2], [6]], [[1], [3], [5], [7]]] }");
+        assert_eq!(
+            format!("{:?}", BTree::<usize>::new(4)),
+            "BTree { order: 4, len: 0, levels: [[[]]] }");
+    }
+
+    #[test]
+    fn display_draws_the_tree() {
+        let mut tree = BTree::new(3);
+        for key in 1..=7 {
+            let _ = tree.add(key);
+        }
+        let _ = tree.add(8);
+
+        assert_eq!(tree.to_string(), "\
+[4]
+├── [2]
+│   ├── [1]
+│   └── [3]
+└── [6]
+    ├── [5]
+    └── [7, 8]
+");
+        assert_eq!(BTree::<usize>::new(3).to_string(), "[]\n");
+    }
+}
@@ -0,0 +1,356 @@
+use crate::node::NodeRef;
+use crate::BTree;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// A B-tree rule broken by a single node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeViolation {
+    /// Keys are not in strictly ascending order
+    UnsortedKeys,
+    /// More than `order - 1` keys
+    TooManyKeys,
+    /// Fewer keys than the minimum for a non root node, or an empty inner root
+    TooFewKeys,
+    /// An inner node without exactly one more child than keys
+    ChildCountMismatch,
+    /// A key outside the range its parent's keys allow for this node
+    KeyOutOfRange,
+    /// The node's parent pointer does not lead to the node holding it
+    BrokenParentLink,
+    /// `index_in_parent` does not match the node's position in its parent
+    WrongIndexInParent,
+    /// A leaf at a different depth than the leftmost leaf
+    UnevenLeafDepth,
+    /// The recorded subtree size or node count does not add up to the node's
+    /// keys and its children's sizes, or to its children's node counts
+    WrongSize,
+}
+
+/// The first broken rule found by `BTree::check_invariants`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantError<K> {
+    /// A node breaks one of the B-tree rules
+    Node { violation: NodeViolation, node: NodeInfo<K> },
+    /// A count the tree keeps up to date does not match its nodes
+    WrongCount { count: &'static str, recorded: usize, actual: usize },
+}
+
+impl<K: fmt::Debug> fmt::Display for InvariantError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantError::Node { violation, node } => write!(
+                f, "{:?} in the node at {:?} with keys {:?}", violation, node.path, node.keys),
+            InvariantError::WrongCount { count, recorded, actual } => write!(
+                f, "the tree records {} {} but holds {}", recorded, count, actual),
+        }
+    }
+}
+
+impl<K: fmt::Debug> Error for InvariantError<K> {}
+
+/// Snapshot of a single node, taken by `TreeInspector`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeInfo<K> {
+    /// Distance from the root, which has a depth of 0
+    pub depth: usize,
+    /// Child indexes to follow from the root to reach the node
+    pub path: Vec<usize>,
+    pub keys: Vec<K>,
+    pub child_count: usize,
+    /// Every rule the node breaks, empty for a valid node
+    pub violations: Vec<NodeViolation>,
+}
+
+impl<K> NodeInfo<K> {
+    pub fn is_leaf(&self) -> bool {
+        self.child_count == 0
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Read only view of a tree's layout, for tests and tooling that need to look at
+/// individual nodes without reaching into the tree's internals
+pub struct TreeInspector<'a, K> {
+    tree: &'a BTree<K>,
+}
+
+impl<K> BTree<K> {
+    pub fn inspect(&self) -> TreeInspector<'_, K> {
+        TreeInspector { tree: self }
+    }
+}
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Check every node against the B-tree rules listed in `NodeViolation`, then
+    /// the key count, node count and height the tree keeps against its nodes
+    ///
+    /// # Returns
+    /// The first violation found, nodes are checked level by level from the root
+    pub fn check_invariants(&self) -> Result<(), InvariantError<K>> {
+        let nodes = self.inspect().nodes();
+
+        if let Some(node) = nodes.iter().find(|node| !node.is_valid()) {
+            return Err(InvariantError::Node { violation: node.violations[0], node: node.clone() });
+        }
+
+        let counts = [
+            ("keys", self.key_count, nodes.iter().map(|node| node.keys.len()).sum()),
+            ("nodes", self.node_count, nodes.len()),
+            ("levels", self.height, self.inspect().height()),
+        ];
+        for (count, recorded, actual) in counts {
+            if recorded != actual {
+                return Err(InvariantError::WrongCount { count, recorded, actual });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<K: Ord + Clone> TreeInspector<'_, K> {
+    pub fn root(&self) -> NodeInfo<K> {
+        self.node(&[]).unwrap()
+    }
+
+    /// The node reached by following the child indexes in `path` from the root
+    pub fn node(&self, path: &[usize]) -> Option<NodeInfo<K>> {
+        let mut node: NodeRef<K> = Rc::clone(&self.tree.root);
+        let mut bounds = (None, None);
+
+        for child_idx in path {
+            let child = {
+                let node_ref = node.borrow();
+                let child = Rc::clone(node_ref.children.get(*child_idx)?);
+                bounds = child_bounds(&node_ref.keys, *child_idx, bounds);
+                child
+            };
+            node = child;
+        }
+
+        Some(describe(&node, path.to_vec(), bounds))
+    }
+
+    /// Every node in breadth first order, left to right within a level
+    pub fn nodes(&self) -> Vec<NodeInfo<K>> {
+        let mut nodes = Vec::new();
+        let mut level = vec![(Rc::clone(&self.tree.root), Vec::new(), (None, None))];
+
+        while !level.is_empty() {
+            let mut next_level = Vec::new();
+
+            for (node, path, bounds) in level {
+                let node_ref = node.borrow();
+                for (child_idx, child) in node_ref.children.iter().enumerate() {
+                    let mut child_path = path.clone();
+                    child_path.push(child_idx);
+                    let child_bounds = child_bounds(&node_ref.keys, child_idx, bounds.clone());
+                    next_level.push((Rc::clone(child), child_path, child_bounds));
+                }
+                drop(node_ref);
+
+                nodes.push(describe(&node, path, bounds));
+            }
+
+            level = next_level;
+        }
+
+        let height = self.height();
+        for node in nodes.iter_mut() {
+            if node.is_leaf() && node.depth + 1 != height {
+                node.violations.push(NodeViolation::UnevenLeafDepth);
+            }
+        }
+
+        nodes
+    }
+
+    /// Number of levels in the tree, following the leftmost path down
+    pub fn height(&self) -> usize {
+        let mut height = 1;
+        let mut node = Rc::clone(&self.tree.root);
+
+        loop {
+            let child = match node.borrow().children.first() {
+                Some(child) => Rc::clone(child),
+                None => return height,
+            };
+            node = child;
+            height += 1;
+        }
+    }
+
+    /// True if no node breaks any of the rules checked by `NodeInfo::violations`
+    pub fn is_valid(&self) -> bool {
+        self.nodes().iter().all(NodeInfo::is_valid)
+    }
+}
+
+type KeyBounds<K> = (Option<K>, Option<K>);
+
+/// The exclusive range the keys of the child at `child_idx` must fall in
+fn child_bounds<K: Clone>(
+    keys: &[K], child_idx: usize, parent_bounds: KeyBounds<K>) -> KeyBounds<K> {
+    let lower = child_idx.checked_sub(1).and_then(|idx| keys.get(idx).cloned());
+    let upper = keys.get(child_idx).cloned();
+    (lower.or(parent_bounds.0), upper.or(parent_bounds.1))
+}
+
+fn describe<K: Ord + Clone>(
+    node: &NodeRef<K>, path: Vec<usize>, bounds: KeyBounds<K>) -> NodeInfo<K> {
+    let node_ref = node.borrow();
+    let keys = node_ref.keys.clone();
+    let mut violations = Vec::new();
+
+    if keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+        violations.push(NodeViolation::UnsortedKeys);
+    }
+
+    if node_ref.is_key_overflowing() {
+        violations.push(NodeViolation::TooManyKeys);
+    }
+
+    let too_few_keys = if node_ref.is_root() {
+        !node_ref.is_leaf() && keys.is_empty()
+    } else {
+        keys.len() < node_ref.min_keys()
+    };
+    if too_few_keys {
+        violations.push(NodeViolation::TooFewKeys);
+    }
+
+    if !node_ref.is_leaf() && node_ref.children.len() != keys.len() + 1 {
+        violations.push(NodeViolation::ChildCountMismatch);
+    }
+
+    let below_lower = bounds.0.is_some_and(|lower| keys.iter().any(|key| *key <= lower));
+    let above_upper = bounds.1.is_some_and(|upper| keys.iter().any(|key| *key >= upper));
+    if below_lower || above_upper {
+        violations.push(NodeViolation::KeyOutOfRange);
+    }
+
+    let children_size: usize = node_ref.children.iter().map(|child| child.borrow().size).sum();
+    let children_nodes: usize = node_ref.children.iter().map(|child| child.borrow().node_count).sum();
+    if node_ref.size != keys.len() + children_size || node_ref.node_count != 1 + children_nodes {
+        violations.push(NodeViolation::WrongSize);
+    }
+
+    for (idx, child) in node_ref.children.iter().enumerate() {
+        let child_ref = child.borrow();
+        let links_back = child_ref.parent.upgrade()
+            .is_some_and(|parent| Rc::ptr_eq(&parent, node));
+
+        if !links_back && !violations.contains(&NodeViolation::BrokenParentLink) {
+            violations.push(NodeViolation::BrokenParentLink);
+        }
+        if child_ref.index_in_parent != Some(idx)
+            && !violations.contains(&NodeViolation::WrongIndexInParent) {
+            violations.push(NodeViolation::WrongIndexInParent);
+        }
+    }
+
+    NodeInfo {
+        depth: path.len(),
+        path,
+        keys,
+        child_count: node_ref.children.len(),
+        violations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::node_utils::recount_to_root;
+
+    fn build_tree() -> BTree<usize> {
+        let mut tree = BTree::new(3);
+        for key in 1..=7 {
+            let _ = tree.add(key);
+        }
+        tree
+    }
+
+    #[test]
+    fn inspect_layout() {
+        let tree = build_tree();
+        let inspector = tree.inspect();
+
+        let root = inspector.root();
+        assert_eq!(root.keys, vec![4]);
+        assert_eq!(root.child_count, 2);
+        assert_eq!(root.depth, 0);
+
+        let node = inspector.node(&[1, 0]).unwrap();
+        assert_eq!(node.keys, vec![5]);
+        assert_eq!(node.depth, 2);
+        assert!(node.is_leaf());
+
+        assert!(inspector.node(&[2]).is_none());
+        assert_eq!(inspector.height(), 3);
+    }
+
+    #[test]
+    fn nodes_are_listed_level_by_level() {
+        let tree = build_tree();
+        let keys: Vec<Vec<usize>> = tree.inspect().nodes()
+            .into_iter()
+            .map(|node| node.keys)
+            .collect();
+
+        assert_eq!(keys, vec![vec![4], vec![2], vec![6], vec![1], vec![3], vec![5], vec![7]]);
+        assert!(tree.inspect().is_valid());
+    }
+
+    #[test]
+    fn violations_are_reported_per_node() {
+        let tree = build_tree();
+        let left = Rc::clone(&tree.root.borrow().children[0]);
+        left.borrow_mut().keys = vec![9];
+        left.borrow().children[1].borrow_mut().index_in_parent = Some(0);
+
+        let node = tree.inspect().node(&[0]).unwrap();
+        assert_eq!(
+            node.violations,
+            vec![NodeViolation::KeyOutOfRange, NodeViolation::WrongIndexInParent]);
+        assert!(tree.inspect().root().is_valid());
+        assert!(!tree.inspect().is_valid());
+    }
+
+    #[test]
+    fn check_invariants_reports_the_first_violation() {
+        let mut tree = build_tree();
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        tree.key_count = 8;
+        let err = tree.check_invariants().err().unwrap();
+        assert_eq!(err, InvariantError::WrongCount { count: "keys", recorded: 8, actual: 7 });
+        assert_eq!(err.to_string(), "the tree records 8 keys but holds 7");
+
+        // cut the right subtree down to a single leaf
+        let right = Rc::clone(&tree.root.borrow().children[1]);
+        right.borrow_mut().children.clear();
+        let err = tree.check_invariants().err().unwrap();
+        assert_eq!(err.to_string(), "WrongSize in the node at [1] with keys [6]");
+
+        recount_to_root(&right);
+        let err = tree.check_invariants().err().unwrap();
+        assert!(matches!(
+            err,
+            InvariantError::Node { violation: NodeViolation::UnevenLeafDepth, .. }));
+        assert_eq!(err.to_string(), "UnevenLeafDepth in the node at [1] with keys [6]");
+    }
+
+    #[test]
+    fn check_invariants_counts_the_nodes_below() {
+        let tree = build_tree();
+        tree.root.borrow_mut().node_count += 1;
+        let err = tree.check_invariants().err().unwrap();
+        assert!(matches!(err, InvariantError::Node { violation: NodeViolation::WrongSize, .. }));
+    }
+}
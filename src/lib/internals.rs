@@ -0,0 +1,95 @@
+//! Node level building blocks of the tree, for experimenting with custom balancing
+//! policies or hybrid structures without forking the crate.
+//!
+//! **Unstable:** only available with the `unstable-internals` feature. Nothing in
+//! this module follows semver, any of it can change or disappear in a minor release.
+//!
+//! The tree is made of `NodeRef`s (`Rc<RefCell<Node<K>>>`) where every child keeps a
+//! weak pointer to its parent and its own index in the parent's `children`. Code
+//! building on these primitives has to keep those links up to date.
+
+use crate::node::node_utils::recount_subtree;
+use crate::BTree;
+
+pub use crate::btree_delete_leaf::merge_children;
+pub use crate::btree_delete_leaf::shift_key_from_sibling as rotate_key_from_sibling;
+pub use crate::node::node_utils::new_node_ref;
+pub use crate::node::search_status::SearchStatus;
+pub use crate::node::{Node, NodeRef};
+
+impl<K: Ord> BTree<K> {
+    /// The tree's root node
+    pub fn root_node(&self) -> NodeRef<K> {
+        self.root.clone()
+    }
+
+    /// Wrap a hand built node structure into a tree. The structure is used as is,
+    /// `inspect().is_valid()` tells whether it follows the B-tree rules
+    pub fn from_root(order: usize, root: NodeRef<K>) -> BTree<K> {
+        let mut tree = BTree::new(order);
+        tree.root = root;
+        recount_subtree(&tree.root);
+        let levels = tree.key_counts_by_level();
+        tree.node_count = levels.iter().map(Vec::len).sum();
+        tree.key_count = levels.iter().flatten().sum();
+        tree.height = levels.len();
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn build_a_tree_from_primitives() {
+        let root = new_node_ref(3);
+        let mut left = Node::new(3);
+        left.add_key(1);
+        let mut right = Node::new(3);
+        right.add_key(5);
+        right.add_key(7);
+
+        for child in [left, right] {
+            let child = Rc::new(std::cell::RefCell::new(child));
+            child.borrow_mut().parent = Rc::downgrade(&root);
+            root.borrow_mut().add_child(child);
+        }
+        root.borrow_mut().add_key(3);
+
+        let mut tree = BTree::from_root(3, root);
+        assert!(tree.inspect().is_valid());
+
+        // pull 3 down to the left and 5 up from the right
+        let root = tree.root_node();
+        assert!(rotate_key_from_sibling(&root, 1, 0));
+        assert_eq!(tree.inspect().root().keys, vec![5]);
+        assert!(tree.inspect().is_valid());
+
+        let status = root.borrow().search_keys(|keys| keys.binary_search(&5));
+        match status {
+            SearchStatus::Found(idx) => assert_eq!(idx, 0),
+            SearchStatus::NotFound(_) => panic!("5 should be in the root"),
+        }
+        assert!(tree.add(6).is_ok());
+    }
+
+    #[test]
+    fn primitives_never_borrow_two_nodes_at_once() {
+        // a broken shape whose root holds the same leaf twice, rotating and
+        // merging through it used to fail with a BorrowMutError
+        let root = new_node_ref(4);
+        let leaf = new_node_ref(4);
+        leaf.borrow_mut().keys = vec![1, 2, 3];
+        leaf.borrow_mut().parent = Rc::downgrade(&root);
+        root.borrow_mut().keys.push(5);
+        root.borrow_mut().children = vec![Rc::clone(&leaf), Rc::clone(&leaf)];
+
+        assert!(rotate_key_from_sibling(&root, 1, 0));
+        assert_eq!(leaf.borrow().keys, vec![2, 3, 5]);
+        merge_children(&root, 0, 1);
+        assert_eq!(leaf.borrow().keys, vec![1, 2, 3, 5]);
+        assert!(!BTree::from_root(4, root).inspect().is_valid());
+    }
+}
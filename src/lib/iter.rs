@@ -0,0 +1,222 @@
+use crate::node::NodeRef;
+use crate::{default_order, BTree, Change, ChangeKind, Range};
+use std::mem;
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Iterate over copies of every key in ascending order
+    pub fn iter(&self) -> Range<'_, K> {
+        self.range(..)
+    }
+}
+
+impl<'a, K: Ord + Clone> IntoIterator for &'a BTree<K> {
+    type Item = K;
+    type IntoIter = Range<'a, K>;
+
+    fn into_iter(self) -> Range<'a, K> {
+        self.iter()
+    }
+}
+
+/// Iterator moving the keys out of a tree, see `BTree::into_iter`
+pub struct IntoIter<K> {
+    keys: std::vec::IntoIter<K>,
+}
+
+impl<K> IntoIterator for BTree<K> {
+    type Item = K;
+    type IntoIter = IntoIter<K>;
+
+    /// Take the tree apart, yielding its keys in ascending order without copying them.
+    /// The subscribers and changefeed are dropped along with the tree
+    fn into_iter(self) -> IntoIter<K> {
+        let mut keys = Vec::with_capacity(self.len());
+        take_keys(&self.root, &mut keys);
+        IntoIter { keys: keys.into_iter() }
+    }
+}
+
+impl<K> Iterator for IntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.keys.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<K> DoubleEndedIterator for IntoIter<K> {
+    fn next_back(&mut self) -> Option<K> {
+        self.keys.next_back()
+    }
+}
+
+impl<K> ExactSizeIterator for IntoIter<K> {}
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Empty the tree, yielding its keys in ascending order without copying them.
+    ///
+    /// The tree is emptied right away, keeping its root node, settings and
+    /// subscribers. Subscribers and the changefeed see a delete for each key as it
+    /// is yielded, and for the rest when the iterator is dropped
+    pub fn drain(&mut self) -> Drain<'_, K> {
+        let mut keys = Vec::with_capacity(self.len());
+        take_keys(&self.root, &mut keys);
+        self.root.borrow_mut().recount();
+        #[cfg(feature = "merkle")]
+        self.root.borrow().invalidate_hash();
+
+        self.node_count = 1;
+        self.key_count = 0;
+        self.height = 1;
+        self.run_invariant_check();
+        Drain { tree: self, keys: keys.into_iter() }
+    }
+}
+
+/// Iterator moving the keys out of a tree it empties, see `BTree::drain`
+pub struct Drain<'a, K: Ord + Clone> {
+    tree: &'a mut BTree<K>,
+    keys: std::vec::IntoIter<K>,
+}
+
+impl<K: Ord + Clone> Iterator for Drain<'_, K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        let key = self.keys.next()?;
+        if self.tree.is_observed() {
+            self.tree.record_change(Change { key: key.clone(), kind: ChangeKind::Deleted });
+        }
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<K: Ord + Clone> ExactSizeIterator for Drain<'_, K> {}
+
+impl<K: Ord + Clone> Drop for Drain<'_, K> {
+    fn drop(&mut self) {
+        if !self.tree.is_observed() {
+            return;
+        }
+        for key in self.keys.by_ref() {
+            self.tree.record_change(Change { key, kind: ChangeKind::Deleted });
+        }
+    }
+}
+
+/// Move every key of the subtree into `out` in ascending order
+///
+/// # Returns
+/// The number of nodes emptied
+pub(crate) fn take_keys<K>(node: &NodeRef<K>, out: &mut Vec<K>) -> usize {
+    let (keys, children) = {
+        let mut node = node.borrow_mut();
+        node.size = 0;
+        (mem::take(&mut node.keys), mem::take(&mut node.children))
+    };
+
+    let mut node_count = 1;
+    let mut children = children.into_iter();
+    for key in keys {
+        if let Some(child) = children.next() {
+            node_count += take_keys(&child, out);
+        }
+        out.push(key);
+    }
+
+    if let Some(child) = children.next() {
+        node_count += take_keys(&child, out);
+    }
+    node_count
+}
+
+impl<K: Ord> FromIterator<K> for BTree<K> {
+    /// Bulk build a tree of `default_order::<K>()` from keys in any order, dropping duplicates
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut keys: Vec<K> = iter.into_iter().collect();
+        keys.sort_unstable();
+        keys.dedup();
+        BTree::from_sorted_keys(default_order::<K>(), keys.into_iter())
+    }
+}
+
+impl<K: Ord + Clone> Extend<K> for BTree<K> {
+    /// Add every key, skipping the ones already in the tree
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            let _ = self.add(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{default_order, BTree};
+
+    #[test]
+    fn collect_and_consume() {
+        let tree: BTree<usize> = (0..300).rev().map(|k| k % 250).collect();
+        assert_eq!(tree.order(), default_order::<usize>());
+        assert!(tree.inspect().is_valid());
+
+        let mut expected = 0;
+        for key in &tree {
+            assert_eq!(key, expected);
+            expected += 1;
+        }
+        assert_eq!(expected, 250);
+
+        let keys: Vec<usize> = tree.into_iter().collect();
+        assert_eq!(keys, (0..250).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn extend_skips_existing_keys() {
+        let mut tree = BTree::new(3);
+        tree.extend([5, 1, 3]);
+        tree.extend(vec![3, 4, 1, 2]);
+
+        assert!(tree.inspect().is_valid());
+        assert_eq!(tree.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn drain_empties_the_tree() {
+        let mut tree = BTree::builder(3).changefeed().build();
+        tree.extend((0..100).map(|k| k.to_string()));
+        let root = tree.root.clone();
+
+        let mut drain = tree.drain();
+        assert_eq!(drain.len(), 100);
+        assert_eq!(drain.next().as_deref(), Some("0"));
+        assert_eq!(drain.next().as_deref(), Some("1"));
+        drop(drain);
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 1);
+        assert!(std::rc::Rc::ptr_eq(&root, &tree.root));
+        assert!(tree.inspect().is_valid());
+        assert_eq!(tree.changes_since(100).unwrap().count(), 100);
+
+        assert!(tree.add(String::from("a")).is_ok());
+        assert_eq!(tree.drain().collect::<Vec<_>>(), ["a"]);
+    }
+
+    #[test]
+    fn owned_keys_are_moved_out() {
+        let tree: BTree<String> = ["b", "c", "a"].into_iter().map(String::from).collect();
+        let mut keys = tree.into_iter();
+
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys.next_back().as_deref(), Some("c"));
+        assert_eq!(keys.collect::<Vec<_>>(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::io::{self, Read, Write};
+
+/// Keys with a fixed byte encoding, used by the binary dump, the sorted run
+/// export and merkle hashing. Integers are written little endian, with `usize`
+/// and `isize` widened to 8 bytes so dumps move between platforms
+pub trait BinaryKey: Sized {
+    fn write_key<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn read_key<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+/// Keys that can be placed on a number line, needed by `SearchMode::Interpolation`
+pub trait InterpolationKey: Ord {
+    /// Position of the key, the order of positions has to match the order of keys
+    fn position(&self) -> f64;
+}
+
+/// Keys made of a sequence that can be scanned by prefix, needed by
+/// `BTree::iter_prefix`
+pub trait PrefixKey: Ord + Sized {
+    /// The smallest key that sorts after every key starting with this one,
+    /// `None` if no key does
+    fn prefix_successor(&self) -> Option<Self>;
+}
+
+macro_rules! integer_keys {
+    ($($int:ty => $wire:ty),*) => {$(
+        impl BinaryKey for $int {
+            fn write_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&(*self as $wire).to_le_bytes())
+            }
+
+            fn read_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+                let mut buf = [0_u8; std::mem::size_of::<$wire>()];
+                reader.read_exact(&mut buf)?;
+                <$int>::try_from(<$wire>::from_le_bytes(buf))
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            }
+        }
+
+        impl InterpolationKey for $int {
+            fn position(&self) -> f64 {
+                *self as f64
+            }
+        }
+    )*};
+}
+
+integer_keys!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => u64,
+    i8 => i8, i16 => i16, i32 => i32, i64 => i64, i128 => i128, isize => i64
+);
+
+/// Written as a `u64` byte length followed by the UTF-8 bytes
+impl BinaryKey for String {
+    fn write_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_bytes(writer, self.as_bytes())
+    }
+
+    fn read_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+        String::from_utf8(read_bytes(reader)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Written as a `u64` byte length followed by the bytes
+impl BinaryKey for Vec<u8> {
+    fn write_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_bytes(writer, self)
+    }
+
+    fn read_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+        read_bytes(reader)
+    }
+}
+
+/// Drops the trailing `0xff` bytes and increments the last byte left
+impl PrefixKey for Vec<u8> {
+    fn prefix_successor(&self) -> Option<Self> {
+        let last = self.iter().rposition(|byte| *byte != u8::MAX)?;
+        let mut successor = self[..=last].to_vec();
+        successor[last] += 1;
+        Some(successor)
+    }
+}
+
+/// Drops the trailing `char::MAX`s and moves the last char left to the next
+/// one. UTF-8 keeps the order of code points, so this follows the byte order
+impl PrefixKey for String {
+    fn prefix_successor(&self) -> Option<Self> {
+        let mut successor = self.clone();
+        while let Some(last) = successor.pop() {
+            let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+            if let Some(next) = next {
+                successor.push(next);
+                return Some(successor);
+            }
+        }
+        None
+    }
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = u64::read_key(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<K: BinaryKey>(key: &K) -> (Vec<u8>, K) {
+        let mut out = Vec::new();
+        key.write_key(&mut out).unwrap();
+        let back = K::read_key(&mut out.as_slice()).unwrap();
+        (out, back)
+    }
+
+    #[test]
+    fn integers_are_little_endian() {
+        assert_eq!(round_trip(&7_usize), (7_u64.to_le_bytes().to_vec(), 7));
+        assert_eq!(round_trip(&-2_i16), (vec![0xfe, 0xff], -2));
+        assert_eq!(round_trip(&u128::MAX).1, u128::MAX);
+    }
+
+    #[test]
+    fn strings_are_length_prefixed() {
+        let (out, back) = round_trip(&String::from("key"));
+        assert_eq!(out, [&3_u64.to_le_bytes()[..], b"key"].concat());
+        assert_eq!(back, "key");
+
+        let truncated = &out[..out.len() - 1];
+        let err = String::read_key(&mut &truncated[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn prefix_successors_sort_after_the_prefix() {
+        assert_eq!(vec![1_u8, 2].prefix_successor(), Some(vec![1, 3]));
+        assert_eq!(vec![1_u8, 0xff, 0xff].prefix_successor(), Some(vec![2]));
+        assert_eq!(vec![0xff_u8].prefix_successor(), None);
+        assert_eq!(Vec::<u8>::new().prefix_successor(), None);
+
+        assert_eq!(String::from("ab").prefix_successor(), Some(String::from("ac")));
+        assert_eq!(String::from("a\u{d7ff}").prefix_successor(), Some(String::from("a\u{e000}")));
+        assert_eq!(String::from("z\u{10ffff}").prefix_successor(), Some(String::from("{")));
+        assert_eq!(String::from("\u{10ffff}").prefix_successor(), None);
+    }
+
+    #[test]
+    fn positions_keep_the_key_order() {
+        assert!((-5_i64).position() < 0_i64.position());
+        assert!(u64::MAX.position() > (u64::MAX / 2).position());
+    }
+}
@@ -1,91 +1,385 @@
-use crate::node::search_status::SearchStatus;
 use crate::BTreeError::{NotFound, ValueAlreadyExists};
 use btree_delete_leaf as leaf_delete;
-use node::{node_utils::new_node_ref, Node, NodeRef};
-use std::rc::Rc;
-
+use btree_delete_leaf::Rebalanced;
+use node::search_mode::KeySearch;
+use node::node_utils::{edge_leaf, recount, recount_to_root, relink_children, split_node};
+use node::{Node, NodeRef};
+use std::mem;
+use std::rc::{Rc, Weak};
+use augment::NewSummary;
+use changefeed::Changefeed;
+use events::EventLog;
+use watch::Watchers;
+
+pub use augment::{Augment, KeySum};
+pub use builder::BTreeBuilder;
+pub use collation::{Collated, Collation, PrefixCollation};
+#[cfg(feature = "icu")]
+pub use collation::IcuCollation;
+pub use bulk_remove::ExtractIf;
+pub use changefeed::SequencedChange;
+pub use cursor::{Cursor, CursorMut};
+pub use events::{TreeEvent, TreeStep};
+pub use explain::{SearchReport, VisitedNode};
+pub use inspect::{InvariantError, NodeInfo, NodeViolation, TreeInspector};
+pub use iter::{Drain, IntoIter};
+pub use keys::{BinaryKey, InterpolationKey, PrefixKey};
+pub use map::{BTreeMap, Entry, MapIntoIter, MapRange, OccupiedEntry, VacantEntry};
+#[cfg(feature = "merkle")]
+pub use merkle::{verify_proof, MembershipProof, MerkleHash, ProofStep};
+pub use metrics::Metrics;
+pub use observe::StructureObserver;
+pub use node::search_mode::SearchMode;
+pub use node::search_status::SearchStatus;
+pub use range::Range;
+pub use set_cmp::Diff;
+pub use sorted_run::SORTED_RUN_BLOCK_KEYS;
+pub use stats::TreeStats;
+pub use watch::{Change, ChangeKind, SubscriptionId};
+
+mod augment;
 mod btree_delete_leaf;
+#[cfg(feature = "unstable-internals")]
+pub mod internals;
+mod builder;
+mod bulk_load;
+mod bulk_remove;
+mod changefeed;
+mod clone;
+mod cmp;
+mod collation;
+mod convert;
+mod cursor;
 mod delete_inner;
+mod dot;
+mod dump;
+mod events;
+mod explain;
+mod fmt;
+mod inspect;
+mod iter;
+mod keys;
+mod map;
+mod memory;
+#[cfg(feature = "merkle")]
+mod merkle;
+mod metrics;
 mod node;
+mod observe;
+mod order_stats;
+mod range;
+#[cfg(feature = "serde")]
+mod serialize;
+mod set_cmp;
+mod snapshot;
+mod sorted_run;
+mod split;
+mod stats;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod watch;
+
+/// Estimated node size the default order is picked to fill
+pub const TARGET_NODE_BYTES: usize = 512;
+
+/// Largest order a snapshot or a deserialized tree may ask for. Nodes reserve
+/// room for `order` keys up front, so a larger order read from untrusted input
+/// could exhaust memory before a single key is read
+pub const MAX_ORDER: usize = 1 << 16;
+
+/// Order used when a tree of `K` keys is created without picking one, e.g. by
+/// `default()` or a conversion. It is the largest order whose nodes fit in
+/// `TARGET_NODE_BYTES`
+pub const fn default_order<K>() -> usize {
+    memory::order_for_node_bytes::<K>(TARGET_NODE_BYTES)
+}
 
 #[derive(Debug)]
 pub enum BTreeError {
     ValueAlreadyExists,
-    NotFound
+    NotFound,
+    BudgetExceeded,
+    /// A key added through a cursor doesn't sort between the keys around it
+    UnorderedKey,
+    /// An order below 3, which leaves no room to split a node
+    InvalidOrder,
 }
 
-pub struct BTree {
-    root: NodeRef,
+/// A B-tree holding unique keys of any ordered type `K`
+pub struct BTree<K> {
+    root: NodeRef<K>,
     order: usize,
+    node_count: usize,
+    key_count: usize,
+    height: usize,
+    memory_budget: Option<usize>,
+    key_search: KeySearch<K>,
+    watchers: Watchers<K>,
+    changefeed: Option<Changefeed<K>>,
+    invariant_check: Option<InvariantCheck<K>>,
+    augment: Option<NewSummary<K>>,
+    metrics: Metrics,
+    events: Option<EventLog<K>>,
+    pack_appends: bool,
 }
 
-impl BTree {
+/// Validation run after every change, set by `BTreeBuilder::check_invariants`
+type InvariantCheck<K> = fn(&BTree<K>);
+
+impl<K: Ord> BTree<K> {
     pub fn new(order: usize) -> Self {
-        Self { root: new_node_ref(order), order }
+        BTreeBuilder::new(order).build()
+    }
+
+    /// Start configuring a tree of the given order
+    pub fn builder(order: usize) -> BTreeBuilder<K> {
+        BTreeBuilder::new(order)
     }
 
     /// Add a value into the tree or return an error if the value already exists
     /// Works by searching each node for a possible location in every node
     /// until there is no child to insert it in
-    pub fn add(&mut self, value: usize) -> Result<(), BTreeError> {
-        let node = self.find_insert_node(value)?;
-        node.borrow_mut().add_key(value);
-
-        self.split_if_full(node);
+    /// The key is cloned for the changefeed and subscribers, if there are any
+    pub fn add(&mut self, value: K) -> Result<(), BTreeError>
+        where K: Clone {
+        if !self.is_observed() {
+            return self.insert_key(value);
+        }
+        self.insert_key(value.clone())?;
+        self.record_change(Change { key: value, kind: ChangeKind::Inserted });
         Ok(())
     }
 
-    pub fn delete(&mut self, value: usize) -> Result<(), BTreeError> {
-        let (status, node_to_delete_from): (SearchStatus, NodeRef) = self.find(value);
-        let mut node_to_delete_from_ref = node_to_delete_from.borrow_mut();
-        let key_index_to_delete = status.unwrap();
+    /// Add a key, replacing an equal one that is already stored instead of
+    /// failing like `add`. Hands back the replaced key, if any. The key is
+    /// cloned for the changefeed and subscribers, if there are any
+    pub fn insert(&mut self, value: K) -> Result<Option<K>, BTreeError>
+        where K: Clone {
+        let observed = self.is_observed().then(|| value.clone());
+        let replaced = match self.find(&value) {
+            (SearchStatus::Found(idx), node) => {
+                #[cfg(feature = "merkle")]
+                node.borrow().invalidate_hash();
+                let replaced = mem::replace(&mut node.borrow_mut().keys[idx], value);
+                recount_to_root(&node);
+                Some(replaced)
+            }
+            (SearchStatus::NotFound(idx), leaf) => {
+                self.insert_at(leaf, idx, value)?;
+                None
+            }
+        };
 
-        if !status.is_found() { return Err(NotFound); }
+        if let Some(key) = observed {
+            let kind = if replaced.is_some() { ChangeKind::Replaced } else { ChangeKind::Inserted };
+            self.record_change(Change { key, kind });
+        }
+        Ok(replaced)
+    }
 
-        node_to_delete_from_ref.delete_key(key_index_to_delete);
-        // self.split_if_full(node_to_delete_from); TODO: Fix this
+    pub fn delete(&mut self, value: &K) -> Result<(), BTreeError> {
+        let key_search = self.key_search;
+        let key = self.remove_key(|keys| key_search(keys, value))?;
+        self.record_change(Change { key, kind: ChangeKind::Deleted });
+        Ok(())
+    }
 
-        let parent: Option<NodeRef> = node_to_delete_from_ref.parent.upgrade();
-        let is_leaf: bool = node_to_delete_from_ref.is_leaf();
+    /// Remove the key equal to `value` and hand back the one that was stored,
+    /// `None` if there is no such key. The key is cloned for the changefeed and
+    /// subscribers, if there are any
+    pub fn remove(&mut self, value: &K) -> Option<K>
+        where K: Clone {
+        let key_search = self.key_search;
+        let key = self.remove_key(|keys| key_search(keys, value)).ok()?;
+        if self.is_observed() {
+            self.record_change(Change { key: key.clone(), kind: ChangeKind::Deleted });
+        }
+        Some(key)
+    }
 
-        let child_to_split: Option<NodeRef> = node_to_delete_from_ref
-           .try_clone_child(key_index_to_delete as isize);
+    /// Remove every key, leaving an empty root of the same order. Subscribers and
+    /// the changefeed see a delete for each key and stay registered
+    pub fn clear(&mut self) {
+        let new_root = self.new_node();
+        let old_root = mem::replace(&mut self.root, new_root);
+        self.node_count = 1;
+        self.key_count = 0;
+        self.height = 1;
+        self.notify_root_change();
+        self.run_invariant_check();
+
+        if self.is_observed() {
+            let mut keys = Vec::with_capacity(old_root.borrow().size);
+            iter::take_keys(&old_root, &mut keys);
+            for key in keys {
+                self.record_change(Change { key, kind: ChangeKind::Deleted });
+            }
+        }
+    }
 
-        if child_to_split.is_some() {
-            self.split_if_full(child_to_split.unwrap());
+    /// Whether a key equal to `value` is stored. Like `get`, `floor` and
+    /// `ceiling` it walks down from the root without allocating
+    pub fn contains(&self, value: &K) -> bool {
+        self.find(value).0.is_found()
+    }
+
+    /// A copy of the stored key equal to `value`. Nodes live in `RefCell`s so
+    /// the key can't be borrowed out of the tree
+    pub fn get(&self, value: &K) -> Option<K>
+        where K: Clone {
+        match self.find(value) {
+            (SearchStatus::Found(idx), node) => Some(node.borrow().keys[idx].clone()),
+            (SearchStatus::NotFound(_), _) => None,
         }
+    }
 
-        // Handles root node and safe nodes
-        if node_to_delete_from_ref.has_more_than_min_keys()
-            || node_to_delete_from_ref.has_min_key_count() || parent.is_none() {
-            return Ok(());
+    /// A copy of the largest key at or below `value`
+    pub fn floor(&self, value: &K) -> Option<K>
+        where K: Clone {
+        self.closest(value, |idx, _| idx.checked_sub(1))
+    }
+
+    /// A copy of the smallest key at or above `value`
+    pub fn ceiling(&self, value: &K) -> Option<K>
+        where K: Clone {
+        self.closest(value, |idx, len| (idx < len).then_some(idx))
+    }
+
+    /// Walk down to `value` and copy it if found, otherwise the nearest key
+    /// picked by `pick` from where the search ended in each node and the
+    /// node's key count. The last node picked from holds the nearest key
+    fn closest(&self, value: &K, pick: impl Fn(usize, usize) -> Option<usize>) -> Option<K>
+        where K: Clone {
+        let mut node = Rc::clone(&self.root);
+        let mut nearest: Option<(NodeRef<K>, usize)> = None;
+
+        loop {
+            let (end, child) = {
+                let node_ref = node.borrow();
+                match (self.key_search)(&node_ref.keys, value) {
+                    Ok(idx) => return Some(node_ref.keys[idx].clone()),
+                    Err(idx) => (idx, node_ref.children.get(idx).cloned()),
+                }
+            };
+            if let Some(idx) = pick(end, node.borrow().keys.len()) {
+                nearest = Some((Rc::clone(&node), idx));
+            }
+            match child {
+                Some(child) => node = child,
+                None => break,
+            }
         }
 
-        if !is_leaf {
-            delete_inner::delete_inner(
-                &mut node_to_delete_from_ref, key_index_to_delete);
+        nearest.map(|(node, idx)| node.borrow().keys[idx].clone())
+    }
+
+    /// A copy of the smallest key, found by following the first child of every node
+    pub fn first(&self) -> Option<K>
+        where K: Clone {
+        let leaf = edge_leaf(Rc::clone(&self.root), true);
+        let first = leaf.borrow().keys.first().cloned();
+        first
+    }
+
+    /// A copy of the largest key, found by following the last child of every node
+    pub fn last(&self) -> Option<K>
+        where K: Clone {
+        let leaf = edge_leaf(Rc::clone(&self.root), false);
+        let last = leaf.borrow().keys.last().cloned();
+        last
+    }
+
+    /// Insert a key without recording the change
+    pub(crate) fn insert_key(&mut self, value: K) -> Result<(), BTreeError> {
+        let (node, idx) = self.find_insert_node(&value)?;
+        self.insert_at(node, idx, value)?;
+        Ok(())
+    }
+
+    /// Insert a key at `idx` in `leaf`, the spot a search for it ended on,
+    /// without recording the change
+    ///
+    /// # Returns
+    /// The node and index the key ended up at once full nodes were split
+    pub(crate) fn insert_at(&mut self, leaf: NodeRef<K>, idx: usize, value: K)
+        -> Result<(NodeRef<K>, usize), BTreeError> {
+        self.check_memory_budget(&leaf)?;
+        leaf.borrow_mut().keys.insert(idx, value);
+        self.key_count += 1;
+        recount_to_root(&leaf);
+        self.record_event(TreeStep::KeyAdded);
+
+        let position = self.split_if_full(leaf, idx);
+        self.run_invariant_check();
+        Ok(position)
+    }
+
+    fn run_invariant_check(&self) {
+        if let Some(check) = self.invariant_check {
+            check(self);
         }
+    }
 
-        // Leaf Node Cases
-        else {
-            let index_in_parent = node_to_delete_from_ref.index_in_parent.unwrap();
-            drop(node_to_delete_from_ref);
-            leaf_delete::delete_leaf(parent.unwrap(), index_in_parent);
+    /// Pass a successful change on to any subscribers and the changefeed
+    fn record_change(&mut self, change: Change<K>) {
+        self.notify_watchers(&change);
+        if let Some(feed) = self.changefeed.as_mut() {
+            feed.push(change);
         }
+    }
 
-        return Ok(());
-        // TODO:
-        //    * if it does have children
-        //       - bring up the left or right child key
-        //       - if both left and right have minimum merge them together
-        //       and if the node with deleted node still has minimum keys
-        //       bring up left or right
-        //    * if deletion affects height use parent and sibling to merge nodes together
+    /// Remove the key located by `search`, returning the key that was stored.
+    /// See `find_by` for how `search` is used
+    pub(crate) fn remove_key(
+        &mut self, search: impl Fn(&[K]) -> Result<usize, usize>) -> Result<K, BTreeError> {
+        let (status, node_to_delete_from): (SearchStatus, NodeRef<K>) = self.find_by(search);
+        let key_index_to_delete = match status {
+            SearchStatus::Found(idx) => idx,
+            SearchStatus::NotFound(_) => return Err(NotFound),
+        };
+
+        Ok(self.remove_at(node_to_delete_from, key_index_to_delete).0)
     }
 
-    fn find(&mut self, value: usize) -> (SearchStatus, NodeRef) {
-        let mut node: NodeRef = Rc::clone(&self.root);
-        let mut search_result = node.borrow_mut().find_key_index(value);
+    /// Remove the key at `idx` in `node` and rebalance, without recording the change
+    ///
+    /// # Returns
+    /// The removed key, and the node and index the key that followed it ended
+    /// up at once the tree was rebalanced, `None` if it was the largest key
+    pub(crate) fn remove_at(&mut self, node_to_delete_from: NodeRef<K>, key_index_to_delete: usize)
+        -> (K, Option<(NodeRef<K>, usize)>) {
+        let is_leaf: bool = node_to_delete_from.borrow().is_leaf();
+
+        // keys are only ever taken out of leaves, an inner key is swapped with one first
+        let (removed_key, leaf, mut next) = if is_leaf {
+            let removed_key = node_to_delete_from.borrow_mut().delete_key(key_index_to_delete);
+            let next = key_from(&node_to_delete_from, key_index_to_delete);
+            (removed_key, node_to_delete_from, next)
+        } else {
+            let (removed_key, leaf, next) = delete_inner::delete_inner(&node_to_delete_from, key_index_to_delete);
+            (removed_key, leaf, Some(next))
+        };
+        self.key_count -= 1;
+        recount_to_root(&leaf);
+        self.record_event(TreeStep::KeyRemoved);
+
+        self.rebalance_after_delete(leaf, &mut next);
+        self.run_invariant_check();
+        (removed_key, next)
+    }
+
+    fn find(&self, value: &K) -> (SearchStatus, NodeRef<K>) {
+        let key_search = self.key_search;
+        self.find_by(|keys| key_search(keys, value))
+    }
+
+    /// Walk down from the root running `search` over each node's keys, with the
+    /// same contract as `slice::binary_search`, until the key is found or there
+    /// is no child left to search
+    pub(crate) fn find_by(
+        &self, search: impl Fn(&[K]) -> Result<usize, usize>) -> (SearchStatus, NodeRef<K>) {
+        let mut node: NodeRef<K> = Rc::clone(&self.root);
+        let mut search_result = node.borrow().search_keys(&search);
 
 
         loop {
@@ -94,13 +388,13 @@ impl BTree {
             }
 
             let child_idx = search_result.unwrap() as isize;
-            let node_option = node.borrow_mut().try_clone_child(child_idx);
+            let node_option = node.borrow().try_clone_child(child_idx);
 
             match node_option {
                 None => break,
                 Some(child) => {
                     node = child;
-                    search_result = node.borrow_mut().find_key_index(value);
+                    search_result = node.borrow().search_keys(&search);
                 }
             }
         }
@@ -108,53 +402,244 @@ impl BTree {
         (search_result, node)
     }
 
-    /// Get the node were you would insert the desired value
-    fn find_insert_node(&mut self, value: usize) -> Result<NodeRef, BTreeError> {
-        let (status, insert_node) = self.find(value);
+    /// Get the node were you would insert the desired value and the index in it
+    fn find_insert_node(&self, value: &K) -> Result<(NodeRef<K>, usize), BTreeError> {
+        match self.find(value) {
+            (SearchStatus::Found(_), _) => Err(ValueAlreadyExists),
+            (SearchStatus::NotFound(idx), insert_node) => Ok((insert_node, idx)),
+        }
+    }
+
+    /// Walk up from a node that just lost a key, fixing every node left under the
+    /// minimum by rotating a key in from a sibling or merging with one, then drop
+    /// the root if the merges emptied it. `spot` is moved along with the key it
+    /// points at
+    fn rebalance_after_delete(&mut self, node: NodeRef<K>, spot: &mut Option<(NodeRef<K>, usize)>) {
+        let mut node_ref = node;
+        let mut merged = false;
+
+        loop {
+            #[cfg(feature = "merkle")]
+            merkle::invalidate_rebalanced_hashes(&node_ref);
 
-        if status.is_found() {
-            return Err(ValueAlreadyExists);
+            let parent_option: Option<NodeRef<K>> = node_ref.borrow().parent.upgrade();
+            let parent: NodeRef<K> = match parent_option {
+                Some(parent) => parent,
+                None => {
+                    self.collapse_root();
+                    break;
+                }
+            };
+
+            let index_in_parent = {
+                let node = node_ref.borrow();
+                if node.keys.len() >= node.min_keys() {
+                    break;
+                }
+                node.index_in_parent.unwrap()
+            };
+
+            match leaf_delete::rebalance_child(Rc::clone(&parent), index_in_parent, spot) {
+                Rebalanced::Rotated { left } => {
+                    self.metrics.rotations += 1;
+                    self.notify_rotate(&parent, left);
+                    self.record_event(TreeStep::Rotated);
+                }
+                Rebalanced::Merged { into } => {
+                    self.node_count -= 1;
+                    self.metrics.merges += 1;
+                    self.notify_merge(&parent.borrow().children[into]);
+                    self.record_event(TreeStep::Merged);
+                    merged = true;
+                }
+            }
+            node_ref = parent;
         }
 
-        Ok(insert_node)
+        // the merges took nodes out from under every ancestor of the last parent
+        if merged {
+            recount_to_root(&node_ref);
+        }
     }
 
-    fn split_if_full(&mut self, node: NodeRef) {
+    /// Make the only child of an empty root the new root, shrinking the tree by a level
+    fn collapse_root(&mut self) {
+        let new_root = {
+            let mut root = self.root.borrow_mut();
+            if !root.keys.is_empty() || root.children.len() != 1 {
+                return;
+            }
+            root.children.pop().unwrap()
+        };
+
+        {
+            let mut new_root_ref = new_root.borrow_mut();
+            new_root_ref.parent = Weak::new();
+            new_root_ref.index_in_parent = None;
+        }
+        self.root = new_root;
+        self.node_count -= 1;
+        self.height -= 1;
+        self.notify_root_change();
+        self.record_event(TreeStep::RootCollapsed);
+    }
+
+    /// Split `node` and then each of its ancestors for as long as they are over capacity.
+    ///
+    /// A key appended after every other one would leave each split off left
+    /// node half full, so with `BTreeBuilder::pack_appends` a full node on the
+    /// right edge first hands its smallest key to its left sibling if that has
+    /// room, and is only split once the sibling is full too
+    ///
+    /// # Returns
+    /// The node and index the key at `idx` in `node` was moved to by the splits
+    fn split_if_full(&mut self, node: NodeRef<K>, idx: usize) -> (NodeRef<K>, usize) {
+        let appending = self.pack_appends && {
+            let node_ref = node.borrow();
+            node_ref.is_leaf() && idx + 1 == node_ref.keys.len()
+        } && is_on_right_spine(&node);
         let mut node_ref = Rc::clone(&node);
+        let mut position = (node, idx);
+        let mut split = false;
 
         loop {
+            #[cfg(feature = "merkle")]
+            node_ref.borrow().invalidate_hash();
+
             if !node_ref.borrow_mut().is_key_overflowing() {
                 break;
             }
+            if appending && self.spill_to_left_sibling(&node_ref, &mut position) {
+                break;
+            }
 
-            let (mid_key, right_node) = node_ref.borrow_mut().split_node();
-            let parent_option: Option<NodeRef> = node_ref.borrow_mut().parent.upgrade();
+            let mid_key_idx = node_ref.borrow().keys.len() / 2;
+            // the middle key goes up in front of the node's own slot in the parent
+            let mid_key_parent_idx = node_ref.borrow().index_in_parent.unwrap_or(0);
+            let (mid_key, right_node) = split_node(&node_ref);
+            self.node_count += 1;
+            self.metrics.splits += 1;
+            self.notify_split(&node_ref, &mid_key, &right_node);
+            let parent_option: Option<NodeRef<K>> = node_ref.borrow().parent.upgrade();
             let mut insert_left = false;
 
-            let parent: NodeRef = match parent_option {
+            let parent: NodeRef<K> = match parent_option {
                 Some(node_ref) => Rc::clone(&node_ref),
                 None => {
                     // if we are splitting the root node instantiate a new parent
-                    let new_parent: NodeRef = new_node_ref(self.order);
+                    let new_parent: NodeRef<K> = self.new_node();
+                    self.node_count += 1;
+                    self.height += 1;
                     self.root = Rc::clone(&new_parent); // set the new parent as the root
+                    self.notify_root_change();
                     // if the parent is new the left node needs to be inserted
                     insert_left = true;
                     new_parent
                 }
             };
 
-            let mut parent_node = parent.borrow_mut();
-
-            right_node.borrow_mut().parent = Rc::downgrade(&parent);
-            node_ref.borrow_mut().parent = Rc::downgrade(&parent);
+            if Rc::ptr_eq(&position.0, &node_ref) && position.1 >= mid_key_idx {
+                position = if position.1 == mid_key_idx {
+                    (Rc::clone(&parent), mid_key_parent_idx)
+                } else {
+                    (Rc::clone(&right_node), position.1 - mid_key_idx - 1)
+                };
+            }
 
-            parent_node.add_key(mid_key);
+            {
+                let mut parent_node = parent.borrow_mut();
+                parent_node.keys.insert(mid_key_parent_idx, mid_key);
+                if insert_left {
+                    parent_node.children.push(Rc::clone(&node_ref)); // left node
+                }
+                parent_node.children.insert(mid_key_parent_idx + 1, right_node); // right node
+            }
+            relink_children(&parent);
             if insert_left {
-                parent_node.add_child(Rc::clone(&node_ref)); // left node
+                recount(&parent);
             }
-            parent_node.add_child(right_node); // right node
+            self.record_event(TreeStep::Split);
             node_ref = Rc::clone(&parent);
+            split = true;
         }
+
+        // the splits added nodes under every ancestor of the last parent
+        if split {
+            recount_to_root(&node_ref);
+        }
+        position
+    }
+
+    /// Rotate the first key of the overflowing `node` through its parent into
+    /// its left sibling, when there is one with a free slot
+    fn spill_to_left_sibling(&mut self, node: &NodeRef<K>, position: &mut (NodeRef<K>, usize)) -> bool {
+        let (parent, idx) = {
+            let node_ref = node.borrow();
+            match (node_ref.parent.upgrade(), node_ref.index_in_parent) {
+                (Some(parent), Some(idx)) if idx > 0 => (parent, idx),
+                _ => return false,
+            }
+        };
+        let left = Rc::clone(&parent.borrow().children[idx - 1]);
+        if left.borrow().is_full() || !leaf_delete::shift_key_from_sibling(&parent, idx, idx - 1) {
+            return false;
+        }
+        #[cfg(feature = "merkle")]
+        left.borrow().invalidate_hash();
+
+        if Rc::ptr_eq(&position.0, node) {
+            *position = match position.1 {
+                0 => (Rc::clone(&parent), idx - 1),
+                key_idx => (Rc::clone(node), key_idx - 1),
+            };
+        }
+        self.metrics.rotations += 1;
+        self.notify_rotate(&parent, idx - 1);
+        self.record_event(TreeStep::Rotated);
+        true
+    }
+}
+
+/// Whether `node` is the last child of each of its ancestors
+fn is_on_right_spine<K>(node: &NodeRef<K>) -> bool {
+    let mut node = Rc::clone(node);
+    loop {
+        let (parent, idx) = {
+            let node_ref = node.borrow();
+            match node_ref.parent.upgrade() {
+                Some(parent) => (parent, node_ref.index_in_parent),
+                None => return true,
+            }
+        };
+        if idx != Some(parent.borrow().children.len() - 1) {
+            return false;
+        }
+        node = parent;
+    }
+}
+
+/// The key at `idx` of `node`, or when the node has no key there, the first
+/// key of an ancestor that sorts after the node. `None` past the last key
+fn key_from<K>(node: &NodeRef<K>, idx: usize) -> Option<(NodeRef<K>, usize)> {
+    let (mut node, mut idx) = (Rc::clone(node), idx);
+    loop {
+        let parent = {
+            let node_ref = node.borrow();
+            if idx < node_ref.keys.len() {
+                break;
+            }
+            idx = node_ref.index_in_parent?;
+            node_ref.parent.upgrade()?
+        };
+        node = parent;
+    }
+    Some((node, idx))
+}
+
+impl<K: Ord> Default for BTree<K> {
+    /// An empty tree of `default_order::<K>()`
+    fn default() -> Self {
+        BTree::new(default_order::<K>())
     }
 }
 
@@ -165,7 +650,7 @@ mod tests {
     use std::cell::RefCell;
     use std::rc::Rc;
 
-    fn build_tree() -> BTree {
+    fn build_tree() -> BTree<usize> {
         let left_child = Rc::new(RefCell::new(Node::new(3)));
 
         left_child.borrow_mut().add_key(1);
@@ -183,20 +668,23 @@ mod tests {
         root.borrow_mut().children.push(left_child);
         root.borrow_mut().children.push(right_child);
 
-        BTree { root, order: 3 }
+        node::node_utils::recount_subtree(&root);
+        BTree { root, node_count: 3, key_count: 5, height: 2, ..BTree::new(3) }
     }
 
     #[test]
     fn test_find_node() {
-        let mut tree = build_tree();
-        let left_node_test = tree.find_insert_node(2).unwrap();
-        let right_node_test = tree.find_insert_node(8).unwrap();
+        let tree = build_tree();
+        let (left_node_test, left_idx) = tree.find_insert_node(&2).unwrap();
+        let (right_node_test, right_idx) = tree.find_insert_node(&8).unwrap();
+
+        assert_eq!((left_idx, right_idx), (1, 1));
 
         assert_eq!(left_node_test.borrow_mut().keys, vec![1, 3]);
         assert_eq!(right_node_test.borrow_mut().keys, vec![7, 9]);
 
-        let left_node_test = tree.find_insert_node(4).unwrap();
-        let right_node_test = tree.find_insert_node(6).unwrap();
+        let (left_node_test, _) = tree.find_insert_node(&4).unwrap();
+        let (right_node_test, _) = tree.find_insert_node(&6).unwrap();
 
         assert_eq!(left_node_test.borrow_mut().keys, vec![1, 3]);
         assert_eq!(right_node_test.borrow_mut().keys, vec![7, 9]);
@@ -327,6 +815,117 @@ mod tests {
             assert_eq!(level_3_second_child.keys[0], 7);
             assert_eq!(level_3_second_child.keys.len(), 1);
         }
+
+        #[test]
+        fn test_add_string_keys() {
+            let mut tree = BTree::new(3);
+            for key in ["delta", "alpha", "charlie", "bravo"] {
+                assert!(tree.add(key.to_string()).is_ok());
+            }
+            assert!(tree.add(String::from("alpha")).is_err());
+
+            let root = tree.root.borrow();
+            assert_eq!(root.keys, vec!["charlie"]);
+            assert_eq!(root.children[0].borrow().keys, vec!["alpha", "bravo"]);
+            assert_eq!(root.children[1].borrow().keys, vec!["delta"]);
+        }
+
+        #[test]
+        fn test_insert_replaces_equal_keys() {
+            let mut tree = BTree::builder(3).changefeed().build();
+            for key in 0..20 {
+                assert_eq!(tree.insert(key).ok(), Some(None));
+            }
+            assert_eq!(tree.insert(7).ok(), Some(Some(7)));
+            assert!(matches!(tree.add(7), Err(ValueAlreadyExists)));
+
+            assert_eq!(tree.len(), 20);
+            assert!(tree.inspect().is_valid());
+            let kinds: Vec<ChangeKind> = tree.changes_since(19).unwrap()
+                .map(|change| change.change.kind)
+                .collect();
+            assert_eq!(kinds, vec![ChangeKind::Inserted, ChangeKind::Replaced]);
+        }
+    }
+
+    mod lookup_tests {
+        use super::*;
+
+        #[test]
+        fn first_and_last_follow_the_edges() {
+            let mut tree = BTree::new(3);
+            assert_eq!((tree.first(), tree.last()), (None, None));
+
+            for key in (0..100).map(|k| k * 37 % 101) {
+                let _ = tree.add(key);
+            }
+            assert_eq!((tree.first(), tree.last()), (Some(0), Some(100)));
+
+            let _ = tree.delete(&0);
+            let _ = tree.delete(&100);
+            assert_eq!((tree.first(), tree.last()), (Some(1), Some(99)));
+        }
+
+        #[test]
+        fn contains_through_a_shared_reference() {
+            let mut tree = BTree::new(3);
+            for key in 0..20 {
+                let _ = tree.add(key * 2);
+            }
+
+            let shared = &tree;
+            assert!((0..20).all(|key| shared.contains(&(key * 2))));
+            assert!(!(0..20).any(|key| shared.contains(&(key * 2 + 1))));
+        }
+
+        #[test]
+        fn get_returns_the_stored_key() {
+            /// Ordered by id only, so a lookup key can differ from the stored one
+            #[derive(Debug, Clone)]
+            struct User { id: u32, name: &'static str }
+
+            impl User {
+                fn lookup(id: u32) -> Self { User { id, name: "" } }
+            }
+
+            impl PartialEq for User {
+                fn eq(&self, other: &Self) -> bool { self.id == other.id }
+            }
+            impl Eq for User {}
+            impl PartialOrd for User {
+                fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                    Some(self.cmp(other))
+                }
+            }
+            impl Ord for User {
+                fn cmp(&self, other: &Self) -> std::cmp::Ordering { self.id.cmp(&other.id) }
+            }
+
+            let mut tree = BTree::new(3);
+            let _ = tree.add(User { id: 1, name: "ada" });
+            let _ = tree.add(User { id: 2, name: "grace" });
+
+            assert_eq!(tree.get(&User::lookup(2)).map(|user| user.name), Some("grace"));
+            assert_eq!(tree.get(&User::lookup(3)), None);
+        }
+
+        #[test]
+        fn floor_and_ceiling_match_a_scan() {
+            for order in 3..7 {
+                let mut tree = BTree::new(order);
+                for key in (0..100).map(|k| k * 37 % 101) {
+                    let _ = tree.add(key * 3);
+                }
+                let keys: Vec<usize> = tree.iter().collect();
+
+                for value in 0..310 {
+                    let floor = keys.iter().rev().find(|key| **key <= value).copied();
+                    let ceiling = keys.iter().find(|key| **key >= value).copied();
+                    assert_eq!((tree.floor(&value), tree.ceiling(&value)), (floor, ceiling), "{}", value);
+                }
+            }
+            assert_eq!(BTree::<usize>::new(3).floor(&1), None);
+        }
     }
 
     mod delete_key_tests {
@@ -341,12 +940,12 @@ mod tests {
             let _ = tree.add(15);
             let _ = tree.add(1);
 
-            let res = tree.delete(15);
+            let res = tree.delete(&15);
             assert!(res.is_ok());
-            let (res, _) = tree.find(15);
+            let (res, _) = tree.find(&15);
             match res {
-                SearchStatus::NotFound(_) => assert!(true),
-                SearchStatus::Found(_) => assert!(false, "Key 15 should be deleted"),
+                SearchStatus::NotFound(_) => {}
+                SearchStatus::Found(_) => panic!("Key 15 should be deleted"),
             }
 
             let root = tree.root.borrow_mut();
@@ -371,13 +970,13 @@ mod tests {
             let _ = tree.add(15);
             let _ = tree.add(1);
 
-            let _ = tree.delete(15);
-            let res = tree.delete(10);
+            let _ = tree.delete(&15);
+            let res = tree.delete(&10);
             assert!(res.is_ok());
-            let (res, _) = tree.find(10);
+            let (res, _) = tree.find(&10);
             match res {
-                SearchStatus::NotFound(_) => assert!(true),
-                SearchStatus::Found(_) => assert!(false, "Key 15 should be deleted"),
+                SearchStatus::NotFound(_) => {}
+                SearchStatus::Found(_) => panic!("Key 15 should be deleted"),
             }
 
             let root = tree.root.borrow_mut();
@@ -402,8 +1001,8 @@ mod tests {
             let _ = tree.add(15);
             let _ = tree.add(1);
 
-            let _ = tree.delete(1);
-            let res = tree.delete(0);
+            let _ = tree.delete(&1);
+            let res = tree.delete(&0);
             assert!(res.is_ok());
 
             let root = tree.root.borrow_mut();
@@ -424,14 +1023,14 @@ mod tests {
             let mut tree = BTree::new(5);
             let _ = tree.add(0);
             let _ = tree.add(5);
-            let res = tree.delete(5);
+            let res = tree.delete(&5);
 
             assert!(res.is_ok());
-            let (res, _) = tree.find(5);
+            let (res, _) = tree.find(&5);
 
             match res {
-                SearchStatus::NotFound(_) => assert!(true),
-                SearchStatus::Found(_) => assert!(false, "Key 5 should be deleted"),
+                SearchStatus::NotFound(_) => {}
+                SearchStatus::Found(_) => panic!("Key 5 should be deleted"),
             }
         }
 
@@ -448,15 +1047,15 @@ mod tests {
             let _ = tree.add(35);
             let _ = tree.add(40);
 
-            let _ = tree.delete(20);
-            let res = tree.delete(25);
+            let _ = tree.delete(&20);
+            let res = tree.delete(&25);
 
             assert!(res.is_ok());
-            let (res, _) = tree.find(25);
+            let (res, _) = tree.find(&25);
 
             match res {
-                SearchStatus::NotFound(_) => assert!(true),
-                SearchStatus::Found(_) => assert!(false, "Key 5 should be deleted"),
+                SearchStatus::NotFound(_) => {}
+                SearchStatus::Found(_) => panic!("Key 5 should be deleted"),
             }
 
             let root = tree.root.borrow_mut();
@@ -488,7 +1087,7 @@ mod tests {
             let _ = tree.add(35);
             let _ = tree.add(40);
 
-            let res = tree.delete(5);
+            let res = tree.delete(&5);
             assert!(res.is_ok());
 
             let root = tree.root.borrow_mut();
@@ -506,66 +1105,198 @@ mod tests {
             let right_child_keys = &right_child.keys;
             assert_eq!(*right_child_keys, vec![30, 35, 40]);
         }
+
+        #[test]
+        fn test_remove_hands_back_the_stored_key() {
+            let mut tree = BTree::builder(3).changefeed().build();
+            for word in ["fig", "apple", "kiwi", "date", "lime"] {
+                let _ = tree.add(String::from(word));
+            }
+
+            assert_eq!(tree.remove(&String::from("kiwi")).as_deref(), Some("kiwi"));
+            assert_eq!(tree.remove(&String::from("kiwi")), None);
+            assert_eq!(tree.len(), 4);
+
+            let last = tree.changes_since(0).unwrap().last().unwrap();
+            assert_eq!(last.change.kind, ChangeKind::Deleted);
+            assert_eq!(last.change.key, "kiwi");
+        }
     }
 
     mod delete_inner_key_tests {
-        use crate::{BTree, SearchStatus};
+        use crate::BTree;
+
+        /// root [32] over [10, 25] and [40], with leaves
+        /// [0, 5] [15, 20] [30, 31] under the left and [35] [45] under the right
+        fn build_tree() -> BTree<usize> {
+            let mut tree = BTree::new(4);
+            for key in [0, 5, 10, 15, 20, 25, 30, 35, 40, 45, 31, 32] {
+                let _ = tree.add(key);
+            }
+            tree
+        }
+
+        fn node_keys(tree: &BTree<usize>, path: &[usize]) -> Vec<usize> {
+            tree.inspect().node(path).unwrap().keys
+        }
 
         #[test]
-        // TODO: Test out the structure of the tree
         fn delete_inner_key_with_left_child_borrow_test()
         {
-            let mut tree = BTree::new(4);
-            let _ = tree.add(0);
-            let _ = tree.add(5);
-            let _ = tree.add(10);
-            let _ = tree.add(15);
-            let _ = tree.add(20);
-            let _ = tree.add(25);
-            let _ = tree.add(30);
-            let _ = tree.add(35);
-            let _ = tree.add(40);
-            let _ = tree.add(45);
-            let _ = tree.add(31);
-            let _ = tree.add(32);
+            let mut tree = build_tree();
 
-            let res = tree.delete(35);
+            let res = tree.delete(&25);
 
             assert!(res.is_ok());
-            let (res, _) = tree.find(35);
+            assert!(!tree.contains(&25));
+
+            // the predecessor 20 takes the place of 25
+            assert_eq!(node_keys(&tree, &[]), vec![32]);
+            assert_eq!(node_keys(&tree, &[0]), vec![10, 20]);
+            assert_eq!(node_keys(&tree, &[0, 0]), vec![0, 5]);
+            assert_eq!(node_keys(&tree, &[0, 1]), vec![15]);
+            assert_eq!(node_keys(&tree, &[0, 2]), vec![30, 31]);
+            assert!(tree.inspect().is_valid());
+        }
 
-            match res {
-                SearchStatus::NotFound(_) => assert!(true),
-                SearchStatus::Found(_) => assert!(false, "Key 35 should be deleted"),
+        #[test]
+        fn delete_inner_key_with_right_child_borrow_test() {
+            let mut tree = build_tree();
+            let _ = tree.delete(&25);
+
+            // the predecessor's leaf [15] is at the minimum, the successor 30 is used
+            assert!(tree.delete(&20).is_ok());
+
+            assert_eq!(node_keys(&tree, &[0]), vec![10, 30]);
+            assert_eq!(node_keys(&tree, &[0, 1]), vec![15]);
+            assert_eq!(node_keys(&tree, &[0, 2]), vec![31]);
+            assert!(tree.inspect().is_valid());
+        }
+
+        #[test]
+        fn delete_inner_key_with_both_children_at_minimum_test() {
+            let mut tree = build_tree();
+            let _ = tree.delete(&25);
+            let _ = tree.delete(&20);
+
+            // 15 replaces 30 and its empty leaf takes a key from the left sibling
+            assert!(tree.delete(&30).is_ok());
+            assert_eq!(node_keys(&tree, &[0]), vec![5, 15]);
+            assert_eq!(tree.inspect().node(&[0]).unwrap().child_count, 3);
+            assert_eq!(node_keys(&tree, &[0, 1]), vec![10]);
+
+            // 10 replaces 15 and its empty leaf is merged into the left sibling
+            let memory = tree.memory_usage();
+            assert!(tree.delete(&15).is_ok());
+            assert_eq!(node_keys(&tree, &[0]), vec![10]);
+            assert_eq!(node_keys(&tree, &[0, 0]), vec![0, 5]);
+            assert_eq!(node_keys(&tree, &[0, 1]), vec![31]);
+            assert!(tree.memory_usage() < memory);
+            assert!(tree.inspect().is_valid());
+        }
+
+        #[test]
+        fn delete_root_key_test() {
+            let mut tree = build_tree();
+
+            assert!(tree.delete(&32).is_ok());
+            assert_eq!(node_keys(&tree, &[]), vec![31]);
+            assert_eq!(node_keys(&tree, &[0, 2]), vec![30]);
+            assert!(tree.inspect().is_valid());
+            for key in [0, 5, 10, 15, 20, 25, 30, 31, 35, 40, 45] {
+                assert!(tree.contains(&key), "{} should still be in the tree", key);
             }
+        }
+    }
 
-            let root = tree.root.borrow_mut();
-            let key_vec = &root.keys;
-            assert_eq!(*key_vec, vec![15]);
+    mod rebalance_tests {
+        use crate::BTree;
 
-            let child_count = root.children.len();
-            assert_eq!(child_count, 2);
+        #[test]
+        fn emptied_inner_node_takes_a_key_from_its_sibling() {
+            let mut tree = BTree::new(4);
+            for key in [0, 5, 10, 15, 20, 25, 30, 35, 40, 45, 31, 32] {
+                let _ = tree.add(key);
+            }
 
-            let left_child = root.children[0].borrow_mut();
-            let left_child_keys = &left_child.keys;
-            assert_eq!(*left_child_keys, vec![5]);
+            // [35] merges into [40, 45], leaving [40]'s parent empty
+            assert!(tree.delete(&35).is_ok());
 
-            let right_child = root.children[1].borrow_mut();
-            let right_child_keys = &right_child.keys;
-            assert_eq!(*right_child_keys, vec![25, 32]);
+            let inspect = tree.inspect();
+            assert_eq!(inspect.root().keys, vec![25]);
+            assert_eq!(inspect.node(&[0]).unwrap().keys, vec![10]);
+            assert_eq!(inspect.node(&[1]).unwrap().keys, vec![32]);
+            assert_eq!(inspect.node(&[1, 0]).unwrap().keys, vec![30, 31]);
+            assert_eq!(inspect.node(&[1, 1]).unwrap().keys, vec![40, 45]);
+            assert!(inspect.is_valid());
+        }
+
+        #[test]
+        fn merges_cascade_up_and_collapse_the_root() {
+            let mut tree = BTree::new(3);
+            for key in 1..=7 {
+                let _ = tree.add(key);
+            }
+            assert_eq!(tree.key_counts_by_level().len(), 3);
+
+            assert!(tree.delete(&1).is_ok());
+
+            assert_eq!(tree.key_counts_by_level().len(), 2);
+            assert_eq!(tree.inspect().root().keys, vec![4, 6]);
+            assert!(tree.inspect().is_valid());
+        }
+
+        #[test]
+        fn delete_every_key() {
+            let keys: Vec<usize> = (0..200).map(|k| k * 37 % 211).collect();
+            let mut tree = BTree::new(4);
+            let empty_memory = tree.memory_usage();
+            for key in keys.iter() {
+                let _ = tree.add(*key);
+            }
+
+            for (idx, key) in keys.iter().rev().enumerate() {
+                assert!(tree.delete(key).is_ok());
+                assert_eq!(tree.check_invariants(), Ok(()), "after deleting {}", key);
+                assert!(!tree.contains(key));
+                assert!(keys[..keys.len() - idx - 1].iter().all(|k| tree.contains(k)));
+            }
 
-            let left_child_left_child = left_child.children[0].borrow_mut();
-            let left_child_left_child_keys = &left_child_left_child.keys;
-            assert_eq!(*left_child_left_child_keys, vec![0]);
+            assert_eq!(tree.key_counts_by_level(), vec![vec![0]]);
+            assert_eq!(tree.memory_usage(), empty_memory);
+        }
+    }
+
+    mod clear_tests {
+        use crate::{BTree, ChangeKind};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        #[test]
+        fn clear_resets_the_tree() {
+            let mut tree = BTree::builder(3).changefeed().build();
+            let changed = Rc::new(RefCell::new(Vec::new()));
+            let seen = Rc::clone(&changed);
+            tree.subscribe(10..20, move |change| seen.borrow_mut().push(change.key));
+
+            let empty_memory = tree.memory_usage();
+            for key in 0..50 {
+                let _ = tree.add(key);
+            }
+            tree.clear();
 
-            let left_child_right_child = left_child.children[0].borrow_mut();
-            let left_child_right_child_keys = &left_child_right_child.keys;
-            assert_eq!(*left_child_right_child_keys, vec![10]);
+            assert!(tree.is_empty());
+            assert_eq!((tree.height(), tree.order(), tree.memory_usage()), (1, 3, empty_memory));
+            assert_eq!(tree.first(), None);
+            assert_eq!(changed.borrow().len(), 20);
+            assert_eq!(changed.borrow()[10..], (10..20).collect::<Vec<_>>());
 
-            let right_child_left_child = right_child.children[0].borrow_mut();
-            let right_child_left_child_keys = &right_child_left_child.keys;
-            assert_eq!(*right_child_left_child_keys , vec![0]);
+            let last = tree.changes_since(50).unwrap().last().unwrap();
+            assert_eq!(
+                (last.seq, last.change.key, last.change.kind), (100, 49, ChangeKind::Deleted));
 
+            assert!(tree.add(7).is_ok());
+            assert!(tree.inspect().is_valid());
         }
     }
 }
@@ -0,0 +1,304 @@
+use crate::node::search_status::SearchStatus;
+use crate::{default_order, BTree, BTreeError};
+use std::cmp::Ordering;
+use std::fmt;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use iter::{MapIntoIter, MapRange};
+
+mod entry;
+mod iter;
+
+/// A key and its value, ordered by the key alone so that the tree moves values
+/// along with their keys through every split, merge and rotation
+#[derive(Clone)]
+pub(crate) struct MapEntry<K, V> {
+    pub(crate) key: K,
+    pub(crate) value: V,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for MapEntry<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {:?}", self.key, self.value)
+    }
+}
+
+impl<K: Ord, V> PartialEq for MapEntry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, V> Eq for MapEntry<K, V> {}
+
+impl<K: Ord, V> PartialOrd for MapEntry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for MapEntry<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// A B-tree mapping unique keys to values.
+///
+/// Values live inside the tree's `RefCell` nodes, so they can't be borrowed out
+/// past a call. Read them by copy with `get` or in place with `get_with` and `update`
+pub struct BTreeMap<K, V> {
+    pub(crate) tree: BTree<MapEntry<K, V>>,
+}
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    pub fn new(order: usize) -> Self {
+        Self { tree: BTree::new(order) }
+    }
+
+    /// Add a key and its value or return an error if the key already exists
+    pub fn add(&mut self, key: K, value: V) -> Result<(), BTreeError> {
+        self.tree.insert_key(MapEntry { key, value })
+    }
+
+    /// Add a key and its value, replacing the value if the key is already
+    /// stored. Hands back the replaced value, if any
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, BTreeError> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Ok(Some(entry.insert(value))),
+            Entry::Vacant(entry) => entry.insert(value).map(|_| None),
+        }
+    }
+
+    /// Add `key` with the value returned by `default`, or call `merge` with
+    /// the stored value if the key is already there, in a single search
+    pub fn upsert_with(&mut self, key: K, default: impl FnOnce() -> V, merge: impl FnOnce(&mut V))
+        -> Result<(), BTreeError> {
+        self.entry(key).and_modify(merge).or_insert_with(default).map(|_| ())
+    }
+
+    /// Move every entry of `other` into this map. A key stored in both gets
+    /// the value `resolve` returns from the key, this map's value and
+    /// `other`'s, so replicas can be reconciled with e.g. last writer wins or
+    /// a counter sum. Fails only if the memory budget is exceeded, with the
+    /// entries up to there already merged
+    pub fn merge_with(&mut self, other: BTreeMap<K, V>, mut resolve: impl FnMut(&K, &V, V) -> V)
+        -> Result<(), BTreeError> {
+        for MapEntry { key, value } in other.tree {
+            match self.entry(key) {
+                Entry::Occupied(entry) =>
+                    entry.update_with_key(|key, mine| *mine = resolve(key, mine, value)),
+                Entry::Vacant(entry) => {
+                    entry.insert(value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a key, handing back its value
+    pub fn delete(&mut self, key: &K) -> Result<V, BTreeError> {
+        let entry = self.tree.remove_key(|entries| search(entries, key))?;
+        Ok(entry.value)
+    }
+
+    /// Remove a key, handing back the stored key and its value
+    pub fn remove(&mut self, key: &K) -> Option<(K, V)> {
+        let entry = self.tree.remove_key(|entries| search(entries, key)).ok()?;
+        Some((entry.key, entry.value))
+    }
+
+    /// Keep only the entries `keep` returns true for, see `BTree::retain`
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+        self.tree.retain(|entry| keep(&entry.key, &entry.value));
+    }
+
+    /// Remove every key and value, see `BTree::clear`
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.tree.find_by(|entries| search(entries, key)).0.is_found()
+    }
+
+    /// A copy of the value stored for `key`
+    pub fn get(&self, key: &K) -> Option<V>
+        where V: Clone {
+        self.get_with(key, V::clone)
+    }
+
+    /// Call `f` with the value stored for `key`
+    pub fn get_with<R>(&self, key: &K, f: impl FnOnce(&V) -> R) -> Option<R> {
+        match self.tree.find_by(|entries| search(entries, key)) {
+            (SearchStatus::Found(idx), node) => Some(f(&node.borrow().keys[idx].value)),
+            (SearchStatus::NotFound(_), _) => None,
+        }
+    }
+
+    /// Call `f` to change the value stored for `key` in place
+    pub fn update<R>(&mut self, key: &K, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        match self.tree.find_by(|entries| search(entries, key)) {
+            (SearchStatus::Found(idx), node) => Some(f(&mut node.borrow_mut().keys[idx].value)),
+            (SearchStatus::NotFound(_), _) => None,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.tree.order()
+    }
+
+    /// Rebuild the map with nodes of a different order, see `BTree::change_order`
+    pub fn change_order(&mut self, order: usize) -> Result<(), BTreeError> {
+        self.tree.change_order(order)
+    }
+
+    /// Rebuild the map with full nodes, see `BTree::compact`
+    pub fn compact(&mut self) -> Result<(), BTreeError> {
+        self.tree.compact()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Estimated number of bytes allocated for the map's nodes, see `BTree::memory_usage`
+    pub fn memory_usage(&self) -> usize {
+        self.tree.memory_usage()
+    }
+}
+
+/// Shows the entries of every node level by level, see `BTree`'s `Debug`
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for BTreeMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BTreeMap").field(&self.tree).finish()
+    }
+}
+
+/// Copies every node along with the keys and values, see `BTree::clone`
+impl<K: Ord + Clone, V: Clone> Clone for BTreeMap<K, V> {
+    fn clone(&self) -> Self {
+        BTreeMap { tree: self.tree.clone() }
+    }
+}
+
+impl<K: Ord, V> Default for BTreeMap<K, V> {
+    /// An empty map with the default order for its key and value sizes
+    fn default() -> Self {
+        BTreeMap::new(default_order::<MapEntry<K, V>>())
+    }
+}
+
+/// Locate `key` among entries sorted by key
+fn search<K: Ord, V>(entries: &[MapEntry<K, V>], key: &K) -> Result<usize, usize> {
+    entries.binary_search_by(|entry| entry.key.cmp(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_follow_their_keys_through_splits() {
+        let mut map = BTreeMap::new(3);
+        for key in (0..100).map(|k| k * 37 % 101) {
+            assert!(map.add(key, key.to_string()).is_ok());
+        }
+
+        assert!(map.tree.key_counts_by_level().len() >= 3);
+        for (key, value) in &map {
+            assert_eq!(key.to_string(), value);
+        }
+        assert_eq!(map.get(&74), Some(String::from("74")));
+        assert_eq!(map.get(&101), None);
+    }
+
+    #[test]
+    fn duplicate_keys_are_rejected() {
+        let mut map = BTreeMap::new(3);
+        assert!(map.add("a", 1).is_ok());
+        assert!(matches!(map.add("a", 2), Err(BTreeError::ValueAlreadyExists)));
+        assert_eq!(map.get(&"a"), Some(1));
+        assert_eq!(map.insert("a", 2).ok(), Some(Some(1)));
+        assert_eq!(map.insert("b", 3).ok(), Some(None));
+        assert_eq!(map.get(&"a"), Some(2));
+        assert!(map.delete(&"b").is_ok());
+        assert_eq!(
+            format!("{:?}", map),
+            r#"BTreeMap(BTree { order: 3, len: 1, levels: [[["a": 2]]] })"#);
+    }
+
+    #[test]
+    fn values_follow_their_keys_through_merges() {
+        let mut map = BTreeMap::new(5);
+        for key in (0..9).map(|k| k * 5) {
+            let _ = map.add(key, key * 10);
+        }
+
+        assert_eq!(map.delete(&20).ok(), Some(200));
+        assert_eq!(map.delete(&25).ok(), Some(250));
+        assert!(matches!(map.delete(&25), Err(BTreeError::NotFound)));
+        assert_eq!(map.remove(&30), Some((30, 300)));
+        assert_eq!(map.remove(&30), None);
+
+        assert!(!map.contains_key(&25));
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![
+            (0, 0), (5, 50), (10, 100), (15, 150), (35, 350), (40, 400),
+        ]);
+    }
+
+    #[test]
+    fn values_are_updated_in_place() {
+        let mut map: BTreeMap<&str, Vec<u32>> = BTreeMap::default();
+        let _ = map.add("primes", vec![2, 3]);
+
+        assert_eq!(map.update(&"primes", |primes| { primes.push(5); primes.len() }), Some(3));
+        assert_eq!(map.update(&"evens", |evens| evens.push(2)), None);
+        assert_eq!(map.get_with(&"primes", |primes| primes.iter().sum::<u32>()), Some(10));
+
+        let copy = map.clone();
+        let _ = map.update(&"primes", |primes| primes.clear());
+        assert_eq!(copy.get(&"primes"), Some(vec![2, 3, 5]));
+    }
+
+    #[test]
+    fn upsert_adds_or_merges() {
+        let mut hits = BTreeMap::new(3);
+        for page in ["/", "/about", "/", "/blog", "/"] {
+            assert!(hits.upsert_with(page, || 1, |count| *count += 1).is_ok());
+        }
+        assert_eq!(hits.iter().collect::<Vec<_>>(), vec![("/", 3), ("/about", 1), ("/blog", 1)]);
+    }
+
+    #[test]
+    fn merge_resolves_conflicts_per_key() {
+        // (timestamp, value) registers, the latest write wins
+        let mut mine = BTreeMap::new(3);
+        let mut theirs = BTreeMap::new(4);
+        for key in 0..40 {
+            let _ = mine.add(key, (key % 3, "mine"));
+        }
+        for key in 30..60 {
+            let _ = theirs.add(key, (key % 5, "theirs"));
+        }
+
+        let mut conflicts = Vec::new();
+        let result = mine.merge_with(theirs, |key, mine, theirs| {
+            conflicts.push(*key);
+            if theirs.0 > mine.0 { theirs } else { *mine }
+        });
+        assert!(result.is_ok());
+
+        assert_eq!(conflicts, (30..40).collect::<Vec<_>>());
+        assert_eq!(mine.len(), 60);
+        assert_eq!(mine.get(&31), Some((1, "mine")));
+        assert_eq!(mine.get(&33), Some((3, "theirs")));
+        assert_eq!(mine.get(&50), Some((0, "theirs")));
+        assert!(mine.tree.inspect().is_valid());
+    }
+}
@@ -0,0 +1,184 @@
+use super::{search, BTreeMap, MapEntry};
+use crate::node::search_status::SearchStatus;
+use crate::node::NodeRef;
+use crate::BTreeError;
+
+/// A key's slot in the map, found by a single search, see `BTreeMap::entry`.
+///
+/// Values live inside the tree's `RefCell` nodes, so instead of handing out
+/// `&mut V` an occupied entry reads and changes its value through closures
+pub enum Entry<'a, K, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+/// A key that is not in the map yet, along with the spot it would be added at
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut BTreeMap<K, V>,
+    key: K,
+    leaf: NodeRef<MapEntry<K, V>>,
+    idx: usize,
+}
+
+/// A key in the map and where its entry is stored
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut BTreeMap<K, V>,
+    node: NodeRef<MapEntry<K, V>>,
+    idx: usize,
+}
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    /// Find the entry for `key` to read, change, add or remove it without searching again
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match self.tree.find_by(|entries| search(entries, &key)) {
+            (SearchStatus::Found(idx), node) => Entry::Occupied(OccupiedEntry { map: self, node, idx }),
+            (SearchStatus::NotFound(idx), leaf) => Entry::Vacant(VacantEntry { map: self, key, leaf, idx }),
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    /// Add `default` if the key is vacant
+    pub fn or_insert(self, default: V) -> Result<OccupiedEntry<'a, K, V>, BTreeError> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Add the value returned by `default` if the key is vacant, `default` is
+    /// not called otherwise
+    pub fn or_insert_with(self, default: impl FnOnce() -> V)
+        -> Result<OccupiedEntry<'a, K, V>, BTreeError> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Call `f` with the value if the key is occupied
+    pub fn and_modify(self, f: impl FnOnce(&mut V)) -> Self {
+        match self {
+            Entry::Occupied(entry) => {
+                entry.update(f);
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Add the key with `value`, fails only if the map's memory budget is exceeded
+    pub fn insert(self, value: V) -> Result<OccupiedEntry<'a, K, V>, BTreeError> {
+        let entry = MapEntry { key: self.key, value };
+        let (node, idx) = self.map.tree.insert_at(self.leaf, self.idx, entry)?;
+        Ok(OccupiedEntry { map: self.map, node, idx })
+    }
+}
+
+impl<K: Ord, V> OccupiedEntry<'_, K, V> {
+    /// A copy of the value
+    pub fn get(&self) -> V
+        where V: Clone {
+        self.get_with(V::clone)
+    }
+
+    /// Call `f` with the value
+    pub fn get_with<R>(&self, f: impl FnOnce(&V) -> R) -> R {
+        f(&self.node.borrow().keys[self.idx].value)
+    }
+
+    /// Call `f` to change the value in place
+    pub fn update<R>(&self, f: impl FnOnce(&mut V) -> R) -> R {
+        f(&mut self.node.borrow_mut().keys[self.idx].value)
+    }
+
+    /// Call `f` with the stored key and the value to change in place
+    pub(crate) fn update_with_key<R>(&self, f: impl FnOnce(&K, &mut V) -> R) -> R {
+        let mut node = self.node.borrow_mut();
+        let entry = &mut node.keys[self.idx];
+        f(&entry.key, &mut entry.value)
+    }
+
+    /// Replace the value, handing back the old one
+    pub fn insert(&self, value: V) -> V {
+        self.update(|current| std::mem::replace(current, value))
+    }
+
+    /// Remove the key from the map, handing back its value
+    pub fn remove(self) -> V {
+        self.map.tree.remove_at(self.node, self.idx).0.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTreeMap;
+
+    #[test]
+    fn count_words() {
+        let mut counts = BTreeMap::new(3);
+        let text = "the quick brown fox jumps over the lazy dog the end";
+        for word in text.split(' ') {
+            counts.entry(word).or_insert(0).unwrap().update(|count| *count += 1);
+        }
+
+        assert_eq!(counts.len(), 9);
+        assert_eq!(counts.get(&"the"), Some(3));
+        assert_eq!(counts.get(&"fox"), Some(1));
+        assert!(counts.tree.inspect().is_valid());
+    }
+
+    #[test]
+    fn inserted_entry_follows_splits() {
+        let mut map = BTreeMap::new(3);
+        for key in (0..200).map(|k| k * 37 % 211) {
+            let entry = map.entry(key).or_insert_with(|| key * 10).unwrap();
+            assert_eq!(entry.get(), key * 10);
+            assert_eq!(entry.insert(key * 100), key * 10);
+        }
+
+        assert_eq!(map.len(), 200);
+        assert_eq!(map.get(&74), Some(7400));
+        assert!(map.tree.inspect().is_valid());
+    }
+
+    #[test]
+    fn and_modify_only_runs_on_occupied_keys() {
+        let mut map = BTreeMap::new(4);
+        let _ = map.add("a", 1);
+
+        let _ = map.entry("a").and_modify(|value| *value += 10).or_insert(0);
+        let _ = map.entry("b").and_modify(|value| *value += 10).or_insert(0);
+        assert_eq!((map.get(&"a"), map.get(&"b")), (Some(11), Some(0)));
+
+        let entry = map.entry("b").or_insert_with(|| panic!("b is already in the map"));
+        assert_eq!(entry.map(|entry| entry.get()).ok(), Some(0));
+    }
+
+    #[test]
+    fn occupied_entry_is_removed_without_a_second_search() {
+        let mut map = BTreeMap::new(3);
+        for key in 0..50 {
+            let _ = map.add(key, key.to_string());
+        }
+
+        for key in (0..50).step_by(2) {
+            match map.entry(key) {
+                super::Entry::Occupied(entry) => assert_eq!(entry.remove(), key.to_string()),
+                super::Entry::Vacant(_) => panic!("{} should be in the map", key),
+            }
+        }
+
+        assert_eq!(map.len(), 25);
+        assert!(!map.contains_key(&10) && map.contains_key(&11));
+        assert!(map.tree.inspect().is_valid());
+        assert!(matches!(map.entry(10), super::Entry::Vacant(entry) if *entry.key() == 10));
+    }
+}
@@ -0,0 +1,176 @@
+use super::{search, BTreeMap, MapEntry};
+use crate::range::Range;
+use crate::{default_order, BTree, IntoIter};
+use std::iter;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    /// The entries from `start` up to `end`, searched for by key
+    fn entries(&self, start: Bound<&K>, end: Bound<K>) -> Range<'_, MapEntry<K, V>, K> {
+        Range::new(&self.tree.root, start, end, search::<K, V>)
+    }
+
+    /// Iterate over copies of the keys in ascending order
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_
+        where K: Clone {
+        let mut entries = self.entries(Bound::Unbounded, Bound::Unbounded);
+        iter::from_fn(move || entries.next_with(|entry| entry.key.clone()))
+    }
+
+    /// Iterate over copies of the values in the order of their keys
+    pub fn values(&self) -> impl Iterator<Item = V> + '_
+        where V: Clone {
+        let mut entries = self.entries(Bound::Unbounded, Bound::Unbounded);
+        iter::from_fn(move || entries.next_with(|entry| entry.value.clone()))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> BTreeMap<K, V> {
+    /// Iterate over copies of every key and value in ascending key order
+    pub fn iter(&self) -> MapRange<'_, K, V> {
+        self.range(..)
+    }
+
+    /// Iterate over copies of the keys inside `range` and their values in
+    /// ascending key order, see `BTree::range`
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> MapRange<'_, K, V> {
+        MapRange { entries: self.entries(range.start_bound(), range.end_bound().cloned()) }
+    }
+}
+
+/// Iterator over a range of keys and their values, see `BTreeMap::range`
+pub struct MapRange<'a, K, V> {
+    entries: Range<'a, MapEntry<K, V>, K>,
+}
+
+impl<K: Ord + Clone, V: Clone> Iterator for MapRange<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.entries.next_with(|entry| (entry.key.clone(), entry.value.clone()))
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> IntoIterator for &'a BTreeMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = MapRange<'a, K, V>;
+
+    fn into_iter(self) -> MapRange<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// Iterator moving the keys and values out of a map, see `BTreeMap::into_iter`
+pub struct MapIntoIter<K, V> {
+    entries: IntoIter<MapEntry<K, V>>,
+}
+
+impl<K, V> IntoIterator for BTreeMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = MapIntoIter<K, V>;
+
+    /// Take the map apart, yielding its entries in ascending key order without copying them
+    fn into_iter(self) -> MapIntoIter<K, V> {
+        MapIntoIter { entries: self.tree.into_iter() }
+    }
+}
+
+impl<K, V> Iterator for MapIntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.entries.next().map(|entry| (entry.key, entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for MapIntoIter<K, V> {
+    fn next_back(&mut self) -> Option<(K, V)> {
+        self.entries.next_back().map(|entry| (entry.key, entry.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for MapIntoIter<K, V> {}
+
+impl<K: Ord, V> FromIterator<(K, V)> for BTreeMap<K, V> {
+    /// Bulk build a map of the default order for its entries from entries in
+    /// any order. A key given more than once keeps its last value
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut entries: Vec<MapEntry<K, V>> = iter.into_iter()
+            .map(|(key, value)| MapEntry { key, value })
+            .collect();
+        // the sort is stable, the entries of a key stay in the order they came in
+        entries.sort();
+        entries.dedup_by(|later, kept| {
+            let same_key = later.key == kept.key;
+            if same_key {
+                mem::swap(&mut later.value, &mut kept.value);
+            }
+            same_key
+        });
+        BTreeMap { tree: BTree::from_sorted_keys(default_order::<MapEntry<K, V>>(), entries.into_iter()) }
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for BTreeMap<K, V> {
+    /// Insert every entry, replacing the values of keys already in the map
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            let _ = self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTreeMap;
+
+    fn build_map() -> BTreeMap<usize, String> {
+        let mut map = BTreeMap::new(3);
+        for key in (0..100).map(|k| k * 37 % 101) {
+            let _ = map.add(key * 2, key.to_string());
+        }
+        map
+    }
+
+    #[test]
+    fn iterates_in_key_order() {
+        let map = build_map();
+        let expected: Vec<(usize, String)> = (0..101).filter(|key| *key != 64)
+            .map(|key| (key * 2, key.to_string()))
+            .collect();
+
+        assert_eq!(map.iter().collect::<Vec<_>>(), expected);
+        assert_eq!((&map).into_iter().count(), expected.len());
+        assert_eq!(map.keys().collect::<Vec<_>>(), expected.iter().map(|entry| entry.0).collect::<Vec<_>>());
+        assert_eq!(map.values().collect::<Vec<_>>(), expected.iter().map(|entry| entry.1.clone()).collect::<Vec<_>>());
+        assert_eq!(map.into_iter().rev().collect::<Vec<_>>(), expected.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn ranges_go_by_key() {
+        let map = build_map();
+        let keys = |range: Vec<(usize, String)>| range.into_iter().map(|entry| entry.0).collect::<Vec<_>>();
+
+        assert_eq!(keys(map.range(10..20).collect()), [10, 12, 14, 16, 18]);
+        assert_eq!(keys(map.range(11..=20).collect()), [12, 14, 16, 18, 20]);
+        assert_eq!(keys(map.range(196..).collect()), [196, 198, 200]);
+        assert_eq!(map.range(127..130).next(), None);
+        assert_eq!(map.range(..1).next(), Some((0, String::from("0"))));
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut map: BTreeMap<&str, u32> = [("b", 1), ("a", 2), ("b", 3), ("c", 4), ("b", 5)].into_iter().collect();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("a", 2), ("b", 5), ("c", 4)]);
+        assert!(map.tree.inspect().is_valid());
+
+        map.extend([("d", 6), ("a", 7)]);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("a", 7), ("b", 5), ("c", 4), ("d", 6)]);
+        assert!(BTreeMap::<u8, u8>::from_iter([]).is_empty());
+    }
+}
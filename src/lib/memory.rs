@@ -0,0 +1,188 @@
+use crate::node::{Node, NodeRef};
+use crate::{BTree, BTreeError};
+use std::cell::RefCell;
+use std::mem::size_of;
+use std::rc::Rc;
+
+impl<K: Ord> BTree<K> {
+    /// Limit the estimated bytes the tree's nodes may take up. Inserts that would
+    /// need to allocate past the budget fail with `BudgetExceeded` and leave the
+    /// tree untouched. `None` removes the limit
+    pub fn set_memory_budget(&mut self, max_bytes: Option<usize>) {
+        self.memory_budget = max_bytes;
+    }
+
+    /// Estimated number of bytes allocated for the tree's nodes, not counting
+    /// memory the keys themselves point to
+    pub fn memory_usage(&self) -> usize {
+        self.node_count * node_bytes::<K>(self.order)
+    }
+
+    /// Fail with `BudgetExceeded` if adding a key to `insert_node` would split
+    /// enough nodes to go over the memory budget
+    pub(crate) fn check_memory_budget(&self, insert_node: &NodeRef<K>) -> Result<(), BTreeError> {
+        let budget = match self.memory_budget {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+
+        let new_bytes = nodes_created_by_insert(insert_node) * node_bytes::<K>(self.order);
+        if self.memory_usage() + new_bytes > budget {
+            return Err(BTreeError::BudgetExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// Fail with `BudgetExceeded` if a tree of `node_count` nodes of `order`
+    /// would go over the memory budget, for rebuilds and joins that swap in
+    /// many nodes at once
+    pub(crate) fn check_rebuilt_budget(&self, order: usize, node_count: usize) -> Result<(), BTreeError> {
+        match self.memory_budget {
+            Some(budget) if node_count * node_bytes::<K>(order) > budget => Err(BTreeError::BudgetExceeded),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Estimated size of a single node: the shared allocation plus key and child
+/// storage, counting the extra slot used while a node overflows before a split
+pub(crate) const fn node_bytes<K>(order: usize) -> usize {
+    node_overhead_bytes::<K>() + order * size_of::<K>() + (order + 1) * size_of::<NodeRef<K>>()
+}
+
+const fn node_overhead_bytes<K>() -> usize {
+    size_of::<Rc<()>>() * 2 + size_of::<RefCell<Node<K>>>()
+}
+
+/// The largest order whose nodes fit in `target_bytes`, never less than 3
+pub(crate) const fn order_for_node_bytes<K>(target_bytes: usize) -> usize {
+    let slot_bytes = size_of::<K>() + size_of::<NodeRef<K>>();
+    let fixed_bytes = node_overhead_bytes::<K>() + size_of::<NodeRef<K>>();
+
+    if target_bytes < fixed_bytes + 3 * slot_bytes {
+        return 3;
+    }
+
+    (target_bytes - fixed_bytes) / slot_bytes
+}
+
+/// Count the nodes a split cascade would allocate if a key was added to `node`.
+/// Every full node on the way up splits off a new sibling, and a full root
+/// also needs a new root above it
+fn nodes_created_by_insert<K: Ord>(node: &NodeRef<K>) -> usize {
+    let mut count = 0;
+    let mut current = Rc::clone(node);
+
+    loop {
+        let parent = {
+            let node = current.borrow();
+            if !node.is_full() { return count; }
+            node.parent.upgrade()
+        };
+
+        count += 1;
+        match parent {
+            Some(parent) => current = parent,
+            None => return count + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{node_bytes, order_for_node_bytes};
+    use crate::{default_order, BTree, BTreeError, TARGET_NODE_BYTES};
+    use crate::node::NodeRef;
+
+    #[test]
+    fn new_tree_uses_one_node() {
+        let tree = BTree::<usize>::new(3);
+        assert_eq!(tree.memory_usage(), node_bytes::<usize>(3));
+    }
+
+    #[test]
+    fn usage_grows_with_splits() {
+        let mut tree = BTree::<usize>::new(3);
+        let _ = tree.add(1);
+        let _ = tree.add(2);
+        assert_eq!(tree.memory_usage(), node_bytes::<usize>(3));
+
+        // root splits into two children and a new root
+        let _ = tree.add(3);
+        assert_eq!(tree.memory_usage(), 3 * node_bytes::<usize>(3));
+    }
+
+    #[test]
+    fn insert_past_budget_is_rejected() {
+        let mut tree = BTree::<usize>::new(3);
+        tree.set_memory_budget(Some(node_bytes::<usize>(3)));
+
+        assert!(tree.add(1).is_ok());
+        assert!(tree.add(2).is_ok());
+
+        match tree.add(3) {
+            Err(BTreeError::BudgetExceeded) => (),
+            res => panic!("Expected BudgetExceeded, got {:?}", res),
+        }
+
+        assert_eq!(tree.root.borrow().keys, vec![1, 2]);
+        assert_eq!(tree.memory_usage(), node_bytes::<usize>(3));
+    }
+
+    #[test]
+    fn node_storage_matches_the_estimate() {
+        fn capacities(node: &NodeRef<usize>, out: &mut Vec<(usize, usize)>) {
+            let node = node.borrow();
+            out.push((node.keys.capacity(), node.children.capacity()));
+            node.children.iter().for_each(|child| capacities(child, out));
+        }
+
+        // the key and child slots node_bytes counts are reserved up front,
+        // through splits, merges, clones and cuts
+        let mut tree = BTree::<usize>::new(5);
+        for key in (0..400).map(|k| k * 37 % 401) {
+            let _ = tree.add(key);
+        }
+        for key in (0..400).step_by(3) {
+            let _ = tree.delete(&key);
+        }
+        let right = tree.split_off(&200);
+        for tree in [tree.clone(), tree, right] {
+            let mut found = Vec::new();
+            capacities(&tree.root, &mut found);
+            assert!(found.iter().all(|slots| *slots == (5, 6)), "{:?}", found);
+        }
+    }
+
+    #[test]
+    fn budget_counts_the_whole_split_cascade() {
+        let mut tree = BTree::<usize>::new(3);
+        for key in 1..=5 {
+            let _ = tree.add(key);
+        }
+        let _ = tree.add(6);
+        // root [2, 4] over [1] [3] [5, 6]: adding 7 splits the leaf and the
+        // root, then creates a new root
+        let usage = tree.memory_usage();
+
+        tree.set_memory_budget(Some(usage + 2 * node_bytes::<usize>(3)));
+        assert!(matches!(tree.add(7), Err(BTreeError::BudgetExceeded)));
+
+        tree.set_memory_budget(Some(usage + 3 * node_bytes::<usize>(3)));
+        assert!(tree.add(7).is_ok());
+        assert_eq!(tree.memory_usage(), usage + 3 * node_bytes::<usize>(3));
+    }
+
+    #[test]
+    fn order_fills_the_target_node_size() {
+        for target in [256, 512, 1000, 4096] {
+            let order = order_for_node_bytes::<usize>(target);
+            assert!(node_bytes::<usize>(order) <= target);
+            assert!(node_bytes::<usize>(order + 1) > target);
+        }
+
+        assert_eq!(order_for_node_bytes::<usize>(0), 3);
+        assert_eq!(default_order::<usize>(), order_for_node_bytes::<usize>(TARGET_NODE_BYTES));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::node::{Node, NodeRef};
+use crate::{BTree, BinaryKey};
+use sha2::{Digest, Sha256};
+use std::rc::Rc;
+
+/// SHA-256 digest of a node and everything below it
+pub type MerkleHash = [u8; 32];
+
+/// Prefixes keeping leaf and inner node encodings and key sequences from ever colliding
+const LEAF_TAG: u8 = 0;
+const INNER_TAG: u8 = 1;
+const CONTENT_TAG: u8 = 2;
+
+/// A node on the search path of a `MembershipProof`, holding everything needed
+/// to recompute its hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofStep<K> {
+    pub keys: Vec<K>,
+    /// Hashes of every child, empty for a leaf
+    pub child_hashes: Vec<MerkleHash>,
+}
+
+/// The nodes visited while searching for `key`, starting at the root. Checked
+/// with `verify_proof` against a trusted root hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MembershipProof<K> {
+    pub key: K,
+    pub steps: Vec<ProofStep<K>>,
+}
+
+impl<K: Ord + Clone + BinaryKey> BTree<K> {
+    /// Build a proof that `key` is or isn't in the tree
+    pub fn prove(&self, key: &K) -> MembershipProof<K> {
+        let mut steps = Vec::new();
+        let mut node: NodeRef<K> = Rc::clone(&self.root);
+
+        loop {
+            let next = {
+                let node_ref = node.borrow();
+                steps.push(ProofStep {
+                    keys: node_ref.keys.clone(),
+                    child_hashes: node_ref.children.iter()
+                        .map(|child| child.borrow().merkle_hash())
+                        .collect(),
+                });
+
+                match node_ref.keys.binary_search(key) {
+                    Ok(_) => None,
+                    Err(child_idx) => node_ref.try_clone_child(child_idx as isize),
+                }
+            };
+
+            match next {
+                Some(child) => node = child,
+                None => return MembershipProof { key: key.clone(), steps },
+            }
+        }
+    }
+
+    /// Merkle hash of the whole tree, the one membership proofs are checked
+    /// against. Node hashes are cached and only the nodes touched by an `add`
+    /// or `delete` are rehashed.
+    ///
+    /// Each node hashes its keys together with its children's hashes, so the
+    /// hash covers the layout as well as the keys: only trees with identical
+    /// layouts compare equal. The layout depends on the order, on the order the
+    /// keys were added and deleted in and on how the tree was built, so two
+    /// trees holding the same keys usually hash differently. Use `content_hash`
+    /// to compare the keys alone
+    pub fn root_hash(&self) -> MerkleHash {
+        self.root.borrow().merkle_hash()
+    }
+
+    /// Hash of the keys in ascending order, whatever the layout of the nodes
+    /// holding them, so replicas that got the same keys by different routes
+    /// compare equal. Unlike `root_hash` nothing is cached, every call hashes
+    /// all the keys
+    pub fn content_hash(&self) -> MerkleHash {
+        let mut hasher = Sha256::new();
+        hasher.update([CONTENT_TAG]);
+        hasher.update((self.len() as u64).to_le_bytes());
+
+        for key in self.iter() {
+            // writing into the hasher can't fail
+            let _ = key.write_key(&mut hasher);
+        }
+
+        hasher.finalize().into()
+    }
+}
+
+impl<K: BinaryKey> Node<K> {
+    /// Hash of this node, computed from the cached hashes of its children
+    pub(crate) fn merkle_hash(&self) -> MerkleHash {
+        if let Some(hash) = self.hash.get() {
+            return hash;
+        }
+
+        let child_hashes: Vec<MerkleHash> = self.children.iter()
+            .map(|child| child.borrow().merkle_hash())
+            .collect();
+        let hash = hash_node(&self.keys, &child_hashes);
+
+        self.hash.set(Some(hash));
+        hash
+    }
+}
+
+impl<K> Node<K> {
+    /// Drop the cached hash of this node and all of its ancestors
+    pub(crate) fn invalidate_hash(&self) {
+        self.hash.set(None);
+
+        let mut parent = self.parent.upgrade();
+        while let Some(node) = parent {
+            let node = node.borrow();
+            node.hash.set(None);
+            parent = node.parent.upgrade();
+        }
+    }
+}
+
+/// Check a proof against a trusted root hash
+///
+/// # Returns
+/// Some(true) => The proof shows the key is in the tree
+/// Some(false) => The proof shows the key is not in the tree
+/// None => The proof does not match `root_hash` or does not follow the key's search path
+pub fn verify_proof<K: Ord + BinaryKey>(
+    root_hash: &MerkleHash, proof: &MembershipProof<K>) -> Option<bool> {
+    let mut expected_hash = *root_hash;
+
+    for (idx, step) in proof.steps.iter().enumerate() {
+        if hash_node(&step.keys, &step.child_hashes) != expected_hash {
+            return None;
+        }
+
+        let is_last = idx + 1 == proof.steps.len();
+        match step.keys.binary_search(&proof.key) {
+            Ok(_) => return if is_last { Some(true) } else { None },
+            Err(_) if step.child_hashes.is_empty() => {
+                return if is_last { Some(false) } else { None };
+            }
+            Err(child_idx) => expected_hash = *step.child_hashes.get(child_idx)?,
+        }
+    }
+
+    // the path stopped at an inner node that doesn't hold the key
+    None
+}
+
+/// Hash a node from its keys and its children's hashes, with no children for a leaf
+pub(crate) fn hash_node<K: BinaryKey>(keys: &[K], child_hashes: &[MerkleHash]) -> MerkleHash {
+    let mut hasher = Sha256::new();
+    hasher.update([if child_hashes.is_empty() { LEAF_TAG } else { INNER_TAG }]);
+    hasher.update((keys.len() as u64).to_le_bytes());
+
+    for key in keys {
+        // writing into the hasher can't fail
+        let _ = key.write_key(&mut hasher);
+    }
+    for child_hash in child_hashes {
+        hasher.update(child_hash);
+    }
+
+    hasher.finalize().into()
+}
+
+/// Clear the cached hashes of everything a delete can rebalance: the node, its
+/// children and its siblings, along with their ancestors
+pub(crate) fn invalidate_rebalanced_hashes<K>(node: &NodeRef<K>) {
+    let node = node.borrow();
+    node.invalidate_hash();
+    node.children.iter().for_each(|child| child.borrow().invalidate_hash());
+
+    if let Some(parent) = node.parent.upgrade() {
+        parent.borrow().children.iter().for_each(|sibling| sibling.borrow().invalidate_hash());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hash the subtree from scratch, ignoring every cached hash
+    fn uncached_hash(node: &NodeRef<usize>) -> MerkleHash {
+        let node = node.borrow();
+        let child_hashes: Vec<MerkleHash> = node.children.iter().map(uncached_hash).collect();
+        hash_node(&node.keys, &child_hashes)
+    }
+
+    fn build_tree(keys: impl Iterator<Item = usize>) -> BTree<usize> {
+        let mut tree = BTree::new(3);
+        keys.for_each(|key| { let _ = tree.add(key); });
+        tree
+    }
+
+    #[test]
+    fn same_layout_gives_same_hash() {
+        let first = build_tree(0..20);
+        let second = build_tree(0..20);
+        let other = build_tree(1..21);
+
+        assert_eq!(first.root_hash(), second.root_hash());
+        assert_ne!(first.root_hash(), other.root_hash());
+    }
+
+    #[test]
+    fn content_hash_ignores_the_layout() {
+        let ascending = build_tree(0..20);
+        let descending = build_tree((0..20).rev());
+        let other = build_tree(1..21);
+
+        assert_ne!(ascending.root_hash(), descending.root_hash());
+        assert_eq!(ascending.content_hash(), descending.content_hash());
+        assert_ne!(ascending.content_hash(), other.content_hash());
+        assert_eq!(BTree::<usize>::new(3).content_hash(), BTree::<usize>::new(5).content_hash());
+    }
+
+    #[test]
+    fn leaf_and_inner_nodes_hash_differently() {
+        let empty: [usize; 0] = [];
+        assert_ne!(hash_node(&empty, &[]), hash_node(&empty, &[hash_node(&empty, &[])]));
+    }
+
+    #[test]
+    fn cache_follows_inserts() {
+        let mut tree = BTree::new(3);
+
+        for key in (0..100).map(|k| k * 37 % 101) {
+            let before = tree.root_hash();
+            let _ = tree.add(key);
+
+            assert_ne!(tree.root_hash(), before);
+            assert_eq!(tree.root_hash(), uncached_hash(&tree.root));
+        }
+    }
+
+    #[test]
+    fn proofs_verify_membership() {
+        let tree = build_tree((0..50).map(|k| k * 2));
+        let root_hash = tree.root_hash();
+
+        for key in 0..100 {
+            let proof = tree.prove(&key);
+            assert_eq!(verify_proof(&root_hash, &proof), Some(key % 2 == 0), "Proving {}", key);
+        }
+    }
+
+    #[test]
+    fn tampered_proofs_are_rejected() {
+        let tree = build_tree((0..50).map(|k| k * 2));
+        let root_hash = tree.root_hash();
+
+        // claim a key that is absent by slipping it into the leaf
+        let mut proof = tree.prove(&7);
+        proof.steps.last_mut().unwrap().keys.push(7);
+        assert_eq!(verify_proof(&root_hash, &proof), None);
+
+        // hide a present key by cutting the path short
+        let mut proof = tree.prove(&8);
+        proof.steps.pop();
+        assert_eq!(verify_proof(&root_hash, &proof), None);
+
+        // a valid proof for a different tree
+        let other = build_tree((0..50).map(|k| k * 3));
+        assert_eq!(verify_proof(&root_hash, &other.prove(&9)), None);
+    }
+
+    #[test]
+    fn cache_follows_deletes() {
+        let expected = build_tree(0..5);
+
+        let mut tree = build_tree(0..6);
+        let _ = tree.root_hash();
+        let _ = tree.delete(&5);
+
+        assert_eq!(tree.root_hash(), uncached_hash(&tree.root));
+        assert_eq!(tree.root_hash(), expected.root_hash());
+    }
+}
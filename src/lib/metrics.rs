@@ -0,0 +1,50 @@
+use crate::BTree;
+
+/// Rebalancing work a tree has done, see `BTree::metrics`. A high count per
+/// change points at an order too small for the workload
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Full nodes split in two, a root split included
+    pub splits: u64,
+    /// Nodes under the minimum merged with a sibling
+    pub merges: u64,
+    /// Keys rotated in from a sibling to fix a node under the minimum, or out
+    /// of a full node on appends with `BTreeBuilder::pack_appends`
+    pub rotations: u64,
+}
+
+impl<K> BTree<K> {
+    /// Splits, merges and rotations since the tree was created or the
+    /// counts were last reset. Bulk rebuilds like `retain` and `compact` count
+    /// as none of them
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BTree, Metrics};
+
+    #[test]
+    fn rebalancing_is_counted() {
+        let mut tree = BTree::new(3);
+        tree.extend(1..=8);
+        // two of the splits are root splits, leaving [4] over [2] [6] over [1] [3] [5] [7, 8]
+        assert_eq!(tree.metrics(), Metrics { splits: 4, merges: 0, rotations: 0 });
+
+        tree.reset_metrics();
+        let _ = tree.delete(&5);
+        assert_eq!(tree.metrics(), Metrics { splits: 0, merges: 0, rotations: 1 });
+
+        for key in 1..=4 {
+            let _ = tree.delete(&key);
+        }
+        assert!(tree.metrics().merges >= 2);
+        assert_eq!(tree.clone().metrics(), Metrics::default());
+    }
+}
@@ -1,44 +1,63 @@
-use node_utils::new_node_ref;
+use crate::augment::NodeSummary;
 use search_status::SearchStatus;
+#[cfg(feature = "merkle")]
+use std::cell::Cell;
 use std::cell::{RefCell};
 use std::rc::{Rc, Weak};
 
 pub(crate) mod node_child_operations;
 pub(crate) mod node_utils;
+pub(crate) mod search_mode;
 pub(crate) mod search_status;
-mod delete_rebalance;
 
-pub(crate) type NodeRef = Rc<RefCell<Node>>;
-type WeakNodeRef = Weak<RefCell<Node>>;
+pub type NodeRef<K> = Rc<RefCell<Node<K>>>;
+type WeakNodeRef<K> = Weak<RefCell<Node<K>>>;
 
 /// # Node Rules:
 /// * Max number of keys (order - 1)
 /// * Min number of keys `ceil(order/2) - 1`
 /// * Min number of children `ceil(order/2)`
 #[derive(Debug)]
-pub(crate) struct Node {
-    pub parent: WeakNodeRef,
+pub struct Node<K> {
+    pub parent: WeakNodeRef<K>,
     pub index_in_parent: Option<usize>,
-    pub keys: Vec<usize>,
-    pub children: Vec<NodeRef>,
+    pub keys: Vec<K>,
+    pub children: Vec<NodeRef<K>>,
+    /// Number of keys in the node and everything below it
+    pub size: usize,
+    /// Number of nodes in the subtree, this one included
+    pub(crate) node_count: usize,
+    /// Summary of the subtree when the tree keeps an `Augment`
+    pub(crate) summary: Option<NodeSummary<K>>,
+    /// Cached merkle hash, cleared whenever the node or anything below it changes
+    #[cfg(feature = "merkle")]
+    pub hash: Cell<Option<crate::MerkleHash>>,
 
     order: usize,
     min_keys: usize,
 }
 
-impl Node {
+impl<K: Ord> Node<K> {
+    /// An empty node with room for one key and child over the maximum, the
+    /// overflow a node holds until it is split, so its Vecs never grow
     pub fn new(order: usize) -> Self {
         Self {
             parent: Weak::new(),
             index_in_parent: None,
-            keys: Vec::with_capacity(order - 1),
-            children: Vec::with_capacity(order),
+            keys: Vec::with_capacity(order),
+            children: Vec::with_capacity(order + 1),
+            size: 0,
+            node_count: 1,
+            summary: None,
+            #[cfg(feature = "merkle")]
+            hash: Cell::new(None),
             min_keys: (order as f32 / 2_f32).ceil() as usize - 1,
             order,
         }
     }
 
-    pub fn add_key(&mut self, key: usize) {
+    #[cfg(any(test, feature = "unstable-internals"))]
+    pub fn add_key(&mut self, key: K) {
         // add the new key at the end
         self.keys.push(key);
         let mut new_key_idx = self.keys.len() - 1;
@@ -63,8 +82,15 @@ impl Node {
     /// # Returns
     /// Found(i: usize) => The value exists and `i` is the index location
     /// NotFound(i:usize) => The value does not exist and `i` is where the item should be
-    pub fn find_key_index(&self, key: usize) -> SearchStatus {
-        match self.keys.binary_search(&key) {
+    #[cfg(test)]
+    pub fn find_key_index(&self, key: &K) -> SearchStatus {
+        self.search_keys(|keys| keys.binary_search(key))
+    }
+
+    /// Same as `find_key_index` but running `search` over the keys, which has to
+    /// follow the contract of `slice::binary_search`
+    pub fn search_keys(&self, search: impl FnOnce(&[K]) -> Result<usize, usize>) -> SearchStatus {
+        match search(&self.keys) {
             Ok(i) => SearchStatus::Found(i),
             Err(i) => SearchStatus::NotFound(i)
         }
@@ -74,23 +100,14 @@ impl Node {
     /// node that broke off
     ///
     /// # Returns
-    /// (mid_key: usize, right_node: Node) => `mid_key` represents the key in the middle of
+    /// (mid_key: K, right_node: Node) => `mid_key` represents the key in the middle of
     /// node and `right_node` is the node broken off to the right
-    pub fn split_node(&mut self) -> (usize, NodeRef) {
-        let key_len = self.keys.len();
-        let mid_key_idx = key_len / 2;
-
-        let right_node = new_node_ref(self.order);
+    #[cfg(any(test, feature = "unstable-internals"))]
+    pub fn split_node(&mut self) -> (K, NodeRef<K>) {
+        let right_node = node_utils::new_node_ref(self.order);
+        right_node.borrow_mut().summary = self.summary.as_ref().map(|summary| summary.empty());
 
-        let right_keys = self.keys.split_off(mid_key_idx + 1);
-        let mut right_children: Vec<NodeRef> =
-            if self.children.len() > 0 {
-                self.children.split_off(mid_key_idx + 1)
-            }
-            else
-            {
-                Vec::new()
-            };
+        let (mid_key, right_keys, mut right_children) = self.split_off_half();
 
         for (idx, val) in  right_children.iter_mut().enumerate() {
             let mut node = val.borrow_mut();
@@ -98,66 +115,50 @@ impl Node {
             node.index_in_parent = Some(idx);
         }
 
-        let mid_key = self.keys.pop().unwrap();
-
         let mut right_ref = right_node.borrow_mut();
-        right_ref.children = right_children;
-        right_ref.keys = right_keys;
+        right_ref.children.extend(right_children);
+        right_ref.keys.extend(right_keys);
         right_ref.parent = self.parent.clone();
 
+        right_ref.recount();
         drop(right_ref);
         self.update_children_indexes();
+        self.recount();
         (mid_key, right_node)
     }
 
-    pub fn delete_key(&mut self, index: usize) {
-        self.keys.remove(index);
-
-        // merge the children to the left and right of the deleted key
-        let _ = self.merge_child_vectors(index, index + 1);
-    }
-
-    pub fn merge_children(
-        &mut self, merge_into_index: usize, merge_from_index: usize) -> Result<(), String> {
-        let diff = merge_into_index as isize - merge_from_index as isize;
-
-        let parent_key_to_merge = if diff == 1 {
-            merge_from_index
-        } else if diff == -1 {
-            merge_into_index
+    /// Cut the keys and children after the middle key off the node
+    ///
+    /// # Returns
+    /// (mid_key, right_keys, right_children) => the middle key, taken out too, and
+    /// what was cut off
+    pub(crate) fn split_off_half(&mut self) -> (K, Vec<K>, Vec<NodeRef<K>>) {
+        let mid_key_idx = self.keys.len() / 2;
+        let right_keys = self.keys.split_off(mid_key_idx + 1);
+        let right_children = if self.children.is_empty() {
+            Vec::new()
         } else {
-            panic!("Merged children must be next to each other")
+            self.children.split_off(mid_key_idx + 1)
         };
 
-        let parent_key = self.keys.remove(parent_key_to_merge);
-
-        let _ = self.merge_child_vectors(merge_into_index, merge_from_index);
-        self.borrow_child_mut(merge_into_index)
-           .add_key(parent_key);
-
-        self.children.remove(merge_from_index);
-        self.update_children_indexes();
-        Ok(())
+        let mid_key = self.keys.pop().unwrap();
+        (mid_key, right_keys, right_children)
     }
 
-    pub fn merge_child_vectors(
-        &mut self, merge_into: usize, merge_from: usize) -> Result<(), String> {
-
-        let merge_into_child = self.try_clone_child(merge_into as isize)
-           .ok_or(String::from("No child to merge"))?;
-        let mut merge_into_child = merge_into_child.borrow_mut();
-
-        let merge_from_child = self.try_clone_child(merge_from as isize)
-           .ok_or(String::from("No child to merge"))?;
-        let mut merge_from_child = merge_from_child.borrow_mut();
-
-        merge_into_child.keys.append(&mut merge_from_child.keys);
-        merge_into_child.keys.sort_unstable();
-
-        // TODO: Sort the inserted children
-        merge_into_child.children.append(&mut merge_from_child.children);
+    /// Remove the key at `index` from a leaf
+    pub fn delete_key(&mut self, index: usize) -> K {
+        self.keys.remove(index)
+    }
 
-        Ok(())
+    /// Recompute `size`, `node_count` and the summary from the node's keys and
+    /// the ones of its children
+    pub(crate) fn recount(&mut self) {
+        self.size = self.keys.len()
+            + self.children.iter().map(|child| child.borrow().size).sum::<usize>();
+        self.node_count = 1 + self.children.iter().map(|child| child.borrow().node_count).sum::<usize>();
+        if let Some(summary) = &mut self.summary {
+            summary.update(&self.keys, &self.children);
+        }
     }
 
     /// Shows if the key container is over capacity and ready for a split
@@ -165,14 +166,13 @@ impl Node {
         self.keys.len() > self.order - 1
     }
 
-    /// Returns true if the node is the root and has 1 key
-    /// has otherwise if it has ceil(order / 2) - 1 keys
-    pub fn has_min_key_count(&self) -> bool {
-        if self.is_root() {
-            self.keys.len() == 1
-        } else {
-            self.keys.len() == self.min_keys
-        }
+    /// Shows if the node has the max number of keys and will split on the next insert
+    pub fn is_full(&self) -> bool {
+        self.keys.len() >= self.order - 1
+    }
+
+    pub fn min_keys(&self) -> usize {
+        self.min_keys
     }
 
     pub fn has_more_than_min_keys(&self) -> bool {
@@ -191,15 +191,35 @@ impl Node {
         self.children.is_empty()
     }
 
-    fn get_key(&self, index: usize) -> usize {
-        self.keys[index]
+    /// Visit every key in the subtree rooted at this node in ascending order,
+    /// stopping at the first error returned by `f`
+    pub fn try_for_each_key<E>(
+        &self, f: &mut impl FnMut(&K) -> Result<(), E>) -> Result<(), E> {
+        for (idx, key) in self.keys.iter().enumerate() {
+            if let Some(child) = self.children.get(idx) {
+                child.borrow().try_for_each_key(f)?;
+            }
+            f(key)?;
+        }
+
+        match self.children.get(self.keys.len()) {
+            Some(child) => child.borrow().try_for_each_key(f),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(any(test, feature = "unstable-internals"))]
+    fn get_key(&self, index: usize) -> &K {
+        &self.keys[index]
     }
 
-    fn get_min_key(&self) -> usize {
+    #[cfg(any(test, feature = "unstable-internals"))]
+    fn get_min_key(&self) -> &K {
         self.get_key(0)
     }
 
-    fn get_max_key(&self) -> usize {
+    #[cfg(any(test, feature = "unstable-internals"))]
+    fn get_max_key(&self) -> &K {
         self.get_key(self.keys.len() - 1)
     }
 }
@@ -216,11 +236,11 @@ mod tests {
             let mut node = Node::new(5);
             node.keys.push(5);
 
-            let res = node.find_key_index(5);
+            let res = node.find_key_index(&5);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 0);
 
-            let res = node.find_key_index(3);
+            let res = node.find_key_index(&3);
             assert!(!res.is_found());
         }
 
@@ -230,21 +250,21 @@ mod tests {
             node.keys.push(5);
             node.keys.push(7);
 
-            let res = node.find_key_index(5);
+            let res = node.find_key_index(&5);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 0);
 
-            let res = node.find_key_index(7);
+            let res = node.find_key_index(&7);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 1);
 
-            let res = node.find_key_index(3);
+            let res = node.find_key_index(&3);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(6);
+            let res = node.find_key_index(&6);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(8);
+            let res = node.find_key_index(&8);
             assert!(!res.is_found());
         }
 
@@ -253,28 +273,28 @@ mod tests {
             let mut node = Node::new(8);
             node.keys = vec![5, 7, 9];
 
-            let res = node.find_key_index(5);
+            let res = node.find_key_index(&5);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 0);
 
-            let res = node.find_key_index(7);
+            let res = node.find_key_index(&7);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 1);
 
-            let res = node.find_key_index(9);
+            let res = node.find_key_index(&9);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 2);
 
-            let res = node.find_key_index(3);
+            let res = node.find_key_index(&3);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(6);
+            let res = node.find_key_index(&6);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(8);
+            let res = node.find_key_index(&8);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(10);
+            let res = node.find_key_index(&10);
             assert!(!res.is_found());
         }
 
@@ -283,35 +303,35 @@ mod tests {
             let mut node = Node::new(8);
             node.keys = vec![5, 7, 9, 11];
 
-            let res = node.find_key_index(5);
+            let res = node.find_key_index(&5);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 0);
 
-            let res = node.find_key_index(7);
+            let res = node.find_key_index(&7);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 1);
 
-            let res = node.find_key_index(9);
+            let res = node.find_key_index(&9);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 2);
 
-            let res = node.find_key_index(11);
+            let res = node.find_key_index(&11);
             assert!(res.is_found());
             assert_eq!(res.unwrap(), 3);
 
-            let res = node.find_key_index(3);
+            let res = node.find_key_index(&3);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(6);
+            let res = node.find_key_index(&6);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(8);
+            let res = node.find_key_index(&8);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(10);
+            let res = node.find_key_index(&10);
             assert!(!res.is_found());
 
-            let res = node.find_key_index(12);
+            let res = node.find_key_index(&12);
             assert!(!res.is_found());
         }
     }
@@ -325,39 +345,39 @@ mod tests {
             let mut node = Node::new(5);
             node.keys = vec![5, 10, 15, 20];
 
-            match node.find_key_index(3) {
+            match node.find_key_index(&3) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 0, "Value must be 0 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(8) {
+            match node.find_key_index(&8) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 1, "Value must be 1 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(11) {
+            match node.find_key_index(&11) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 2, "Value must be 2 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(18) {
+            match node.find_key_index(&18) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 3, "Value must be 3 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(25) {
+            match node.find_key_index(&25) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 4, "Value must be 4 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
         }
 
@@ -366,46 +386,46 @@ mod tests {
             let mut node = Node::new(5);
             node.keys = vec![5, 10, 15, 20, 25];
 
-            match node.find_key_index(3) {
+            match node.find_key_index(&3) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 0, "Value must be 0 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(8) {
+            match node.find_key_index(&8) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 1, "Value must be 1 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(11) {
+            match node.find_key_index(&11) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 2, "Value must be 2 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(18) {
+            match node.find_key_index(&18) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 3, "Value must be 3 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(23) {
+            match node.find_key_index(&23) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 4, "Value must be 4 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(26) {
+            match node.find_key_index(&26) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 5, "Value must be 5 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
         }
 
@@ -414,18 +434,18 @@ mod tests {
             let mut node = Node::new(5);
             node.keys = vec![5];
 
-            match node.find_key_index(3) {
+            match node.find_key_index(&3) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 0, "Value must be 0 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
 
-            match node.find_key_index(8) {
+            match node.find_key_index(&8) {
                 SearchStatus::NotFound(index) => {
                     assert_eq!(index, 1, "Value must be 1 instead got {}", index)
                 }
-                SearchStatus::Found(_) => assert!(false, "Value"),
+                SearchStatus::Found(_) => panic!("Value"),
             }
         }
     }
@@ -1,25 +1,27 @@
 use crate::{Node, NodeRef};
-use std::{rc::Rc, cell::{Ref, RefMut}};
+use std::rc::Rc;
 
-pub type OpResult = Result<(),()>;
-
-impl Node {
-    pub(super) fn update_children_indexes(&mut self) {
+impl<K: Ord> Node<K> {
+    #[cfg(any(test, feature = "unstable-internals"))]
+    pub(crate) fn update_children_indexes(&mut self) {
         self.children.iter_mut()
            .enumerate()
            .for_each(|(i, c)| c.borrow_mut().index_in_parent = Some(i));
     }
 
-    pub(super) fn borrow_child(&self, index: usize) -> Ref<'_, Node> {
+    #[cfg(any(test, feature = "unstable-internals"))]
+    pub(super) fn borrow_child(&self, index: usize) -> std::cell::Ref<'_, Node<K>> {
         self.children[index].borrow()
     }
 
-    pub(super) fn borrow_child_mut(&self, index: usize) -> RefMut<'_, Node> {
+    #[cfg(any(test, feature = "unstable-internals"))]
+    pub(super) fn borrow_child_mut(&self, index: usize) -> std::cell::RefMut<'_, Node<K>> {
         self.children[index].borrow_mut()
     }
 
     /// Insert child node and put it into the proper order
-    pub fn add_child(&mut self, child: NodeRef) {
+    #[cfg(any(test, feature = "unstable-internals"))]
+    pub fn add_child(&mut self, child: NodeRef<K>) {
         self.children.push(child);
 
         let mut new_child_idx = self.children.len() - 1;
@@ -53,39 +55,13 @@ impl Node {
     }
 
     /// Return a cloned pointer to the child node at a given index
-    pub fn try_clone_child(&self, index: isize) -> Option<NodeRef> {
+    pub fn try_clone_child(&self, index: isize) -> Option<NodeRef<K>> {
         if self.children.is_empty() || index < 0 {
             return Option::None;
         }
 
         Some(Rc::clone(&self.children[index as usize]))
     }
-
-    pub fn try_move_key_from_left_child(&mut self, index: usize) -> OpResult
-    {
-        self.try_move_key_from_child(index, true)
-    }
-
-    pub fn try_move_key_from_right_child(&mut self, index: usize) -> OpResult
-    {
-        self.try_move_key_from_child(index, false)
-    }
-
-    pub fn try_move_key_from_child(&mut self, index: usize, is_left: bool) -> OpResult
-    {
-        let child_ref: NodeRef = self
-            .try_clone_child(index as isize).ok_or(())?;
-            
-        let mut child = child_ref.borrow_mut();
-        let key_idx_to_move = if is_left { 0 } else { child.keys.len() };    
-        if child.has_more_than_min_keys() {
-            let child_key = child.keys.remove(key_idx_to_move);
-            self.add_key(child_key);
-            Ok(())
-        } else {
-            Err(())
-        }
-    }
 }
 
 #[cfg(test)]
@@ -93,16 +69,16 @@ mod child_tests {
     use super::*;
     use std::cell::RefCell;
 
-    fn build_parent_and_two_nodes() -> (Node, NodeRef, NodeRef) {
+    fn build_parent_and_two_nodes() -> (Node<usize>, NodeRef<usize>, NodeRef<usize>) {
         let parent = Node::new(5);
 
-        let first_child: NodeRef = Rc::new(RefCell::new(Node::new(5)));
+        let first_child: NodeRef<usize> = Rc::new(RefCell::new(Node::new(5)));
         first_child.borrow_mut().add_key(1);
 
-        let second_child: NodeRef = Rc::new(RefCell::new(Node::new(5)));
+        let second_child: NodeRef<usize> = Rc::new(RefCell::new(Node::new(5)));
         second_child.borrow_mut().add_key(2);
 
-        return (parent, first_child, second_child);
+        (parent, first_child, second_child)
     }
 
     #[test]
@@ -115,8 +91,8 @@ mod child_tests {
         let first = parent.try_clone_child(0).unwrap();
         let second = parent.try_clone_child(1).unwrap();
 
-        assert_eq!(first.borrow_mut().get_key(0), 1);
-        assert_eq!(second.borrow_mut().get_key(0), 2);
+        assert_eq!(*first.borrow_mut().get_key(0), 1);
+        assert_eq!(*second.borrow_mut().get_key(0), 2);
     }
 
     #[test]
@@ -129,10 +105,10 @@ mod child_tests {
         let first = parent.try_clone_child(0).unwrap();
         let second = parent.try_clone_child(1).unwrap();
 
-        assert_eq!(first.borrow_mut().get_key(0), 1);
-        assert_eq!(first.borrow_mut().get_key(0), 1);
+        assert_eq!(*first.borrow_mut().get_key(0), 1);
+        assert_eq!(*first.borrow_mut().get_key(0), 1);
         assert_eq!(first.borrow_mut().index_in_parent.unwrap(), 0);
-        assert_eq!(second.borrow_mut().get_key(0), 2);
+        assert_eq!(*second.borrow_mut().get_key(0), 2);
         assert_eq!(second.borrow_mut().index_in_parent.unwrap(), 1);
     }
 }
@@ -1,11 +1,99 @@
+//! The helpers that change a `NodeRef` borrow one node at a time, so they
+//! can't fail with a `BorrowMutError` whatever nodes the caller has at hand
+
 use crate::{Node, NodeRef};
 use std::cell::RefCell;
 use std::rc::Rc;
 
-pub(crate) fn calculate_mid(start: isize, end: isize) -> isize {
-    ((end - start) / 2) + start
+pub fn new_node_ref<K: Ord>(order: usize) -> NodeRef<K> {
+    Rc::new(RefCell::new(Node::new(order)))
 }
 
-pub(crate) fn new_node_ref(order: usize) -> NodeRef {
-    Rc::new(RefCell::new(Node::new(order)))
+/// Recompute the size of `node` and then of each of its ancestors, after keys
+/// or children were added to or removed from the node
+pub(crate) fn recount_to_root<K: Ord>(node: &NodeRef<K>) {
+    let mut node = Rc::clone(node);
+    loop {
+        recount(&node);
+        let parent = node.borrow().parent.upgrade();
+        match parent {
+            Some(parent) => node = parent,
+            None => break,
+        }
+    }
+}
+
+/// Recompute `size`, `node_count` and the summary of `node` like `Node::recount`.
+/// The summary is taken out while the node and its children are read, then put back
+pub(crate) fn recount<K: Ord>(node: &NodeRef<K>) {
+    let mut summary = node.borrow_mut().summary.take();
+    let (size, node_count) = {
+        let node = node.borrow();
+        if let Some(summary) = summary.as_mut() {
+            summary.update(&node.keys, &node.children);
+        }
+        let size = node.keys.len() + node.children.iter().map(|child| child.borrow().size).sum::<usize>();
+        let node_count = 1 + node.children.iter().map(|child| child.borrow().node_count).sum::<usize>();
+        (size, node_count)
+    };
+
+    let mut node = node.borrow_mut();
+    node.size = size;
+    node.node_count = node_count;
+    node.summary = summary;
+}
+
+/// Point every child of `node` back at it and at its position
+pub(crate) fn relink_children<K>(node: &NodeRef<K>) {
+    for idx in 0.. {
+        let child = node.borrow().children.get(idx).cloned();
+        let Some(child) = child else { break };
+        let mut child = child.borrow_mut();
+        child.parent = Rc::downgrade(node);
+        child.index_in_parent = Some(idx);
+    }
+}
+
+/// Split the overflowing `node` like `Node::split_node`, the right half taking
+/// the node's parent link. The parent itself is left to the caller
+pub(crate) fn split_node<K: Ord>(node: &NodeRef<K>) -> (K, NodeRef<K>) {
+    let (mid_key, right_node) = {
+        let mut node_ref = node.borrow_mut();
+        let (mid_key, keys, children) = node_ref.split_off_half();
+        let mut right = Node::new(node_ref.order);
+        right.keys.extend(keys);
+        right.children.extend(children);
+        right.parent = node_ref.parent.clone();
+        right.summary = node_ref.summary.as_ref().map(|summary| summary.empty());
+        (mid_key, Rc::new(RefCell::new(right)))
+    };
+
+    relink_children(&right_node);
+    recount(&right_node);
+    recount(node);
+    (mid_key, right_node)
+}
+
+/// Recompute the size of every node in the subtree, children first
+#[cfg(any(test, feature = "unstable-internals"))]
+pub(crate) fn recount_subtree<K: Ord>(node: &NodeRef<K>) {
+    node.borrow().children.iter().for_each(recount_subtree);
+    node.borrow_mut().recount();
+}
+
+/// Follow the first or last child down to a leaf
+pub(crate) fn edge_leaf<K>(node: NodeRef<K>, first: bool) -> NodeRef<K> {
+    let mut node = node;
+
+    loop {
+        let child = {
+            let node_ref = node.borrow();
+            let child = if first { node_ref.children.first() } else { node_ref.children.last() };
+            match child {
+                Some(child) => Rc::clone(child),
+                None => return Rc::clone(&node),
+            }
+        };
+        node = child;
+    }
 }
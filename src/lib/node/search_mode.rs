@@ -0,0 +1,100 @@
+use crate::InterpolationKey;
+use std::cmp::Ordering;
+
+/// Strategy used to locate a key inside a single node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Halve the searched slice on every step
+    #[default]
+    Binary,
+    /// Guess the position from the key's value relative to the ends of the
+    /// searched slice. Beats binary search on large nodes holding uniformly
+    /// distributed keys, degrades towards a linear scan on skewed ones
+    Interpolation,
+}
+
+/// Searches sorted, unique keys with the same contract as `slice::binary_search`
+pub(crate) type KeySearch<K> = fn(&[K], &K) -> Result<usize, usize>;
+
+impl SearchMode {
+    /// The search function implementing this mode
+    pub(crate) fn key_search<K: InterpolationKey>(self) -> KeySearch<K> {
+        match self {
+            SearchMode::Binary => binary_search,
+            SearchMode::Interpolation => interpolation_search,
+        }
+    }
+}
+
+pub(crate) fn binary_search<K: Ord>(keys: &[K], key: &K) -> Result<usize, usize> {
+    keys.binary_search(key)
+}
+
+fn interpolation_search<K: InterpolationKey>(keys: &[K], key: &K) -> Result<usize, usize> {
+    // the key is searched for in keys[low..high]
+    let mut low = 0;
+    let mut high = keys.len();
+
+    while low < high {
+        let (low_key, high_key) = (&keys[low], &keys[high - 1]);
+
+        if key < low_key { return Err(low); }
+        if key > high_key { return Err(high); }
+        if low_key == high_key { return Ok(low); }
+
+        // positions are only used to guess, rounding can't break the search
+        let fraction = (key.position() - low_key.position())
+            / (high_key.position() - low_key.position());
+        let offset = (fraction * (high - 1 - low) as f64) as usize;
+        let probe = low + offset.min(high - 1 - low);
+
+        match keys[probe].cmp(key) {
+            Ordering::Equal => return Ok(probe),
+            Ordering::Less => low = probe + 1,
+            Ordering::Greater => high = probe,
+        }
+    }
+
+    Err(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolation_matches_binary_search() {
+        let key_sets: Vec<Vec<usize>> = vec![
+            vec![],
+            vec![5],
+            vec![5, 10, 15, 20],
+            vec![1, 2, 3, 100, 1000, 1001],
+            vec![0, 7, 8, 9, 10, 11, 12, 13, 50],
+        ];
+        let search = SearchMode::Interpolation.key_search();
+
+        for keys in key_sets {
+            for key in 0..1100 {
+                assert_eq!(
+                    search(&keys, &key),
+                    keys.binary_search(&key),
+                    "Searching {} in {:?}", key, keys);
+            }
+        }
+    }
+
+    #[test]
+    fn interpolation_handles_extreme_keys() {
+        let keys = vec![0, usize::MAX / 2, usize::MAX];
+        let search = SearchMode::Interpolation.key_search();
+
+        assert_eq!(search(&keys, &usize::MAX), Ok(2));
+        assert_eq!(search(&keys, &(usize::MAX / 2)), Ok(1));
+        assert_eq!(search(&keys, &1), Err(1));
+
+        let keys = vec![i64::MIN, -1, 0, i64::MAX];
+        let search = SearchMode::Interpolation.key_search();
+        assert_eq!(search(&keys, &-1), Ok(1));
+        assert_eq!(search(&keys, &5), Err(3));
+    }
+}
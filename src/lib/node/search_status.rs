@@ -1,4 +1,5 @@
-pub(crate) enum SearchStatus {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchStatus {
     Found(usize),    // contains the key's index
     NotFound(usize), // contains the potential index location
 }
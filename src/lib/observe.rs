@@ -0,0 +1,112 @@
+use crate::node::NodeRef;
+use crate::watch::SubscriptionId;
+use crate::BTree;
+
+/// Callbacks for the structural changes of a tree, registered with
+/// `BTree::observe`. Every method does nothing by default, so an observer
+/// only implements the ones it cares about. They are called once the change
+/// is done, e.g. to log the tree's shape or to visualize it
+pub trait StructureObserver<K> {
+    /// A full node was split into `left` and `right`, `separator` moving up
+    /// to their parent
+    fn on_split(&mut self, _left: &[K], _separator: &K, _right: &[K]) {}
+
+    /// A node under the minimum was merged with a sibling and the key between
+    /// them into a node holding `keys`
+    fn on_merge(&mut self, _keys: &[K]) {}
+
+    /// A key was rotated through the parent between siblings holding `left`
+    /// and `right`, `separator` now sitting between them
+    fn on_rotate(&mut self, _left: &[K], _separator: &K, _right: &[K]) {}
+
+    /// Another node became the root, leaving the tree `height` levels high
+    fn on_root_change(&mut self, _height: usize) {}
+}
+
+impl<K> BTree<K> {
+    /// Call `observer` on every split, merge, rotation and root change
+    pub fn observe<O: StructureObserver<K> + 'static>(&mut self, observer: O) -> SubscriptionId {
+        let id = self.watchers.next_id();
+        self.watchers.observers.push((id, Box::new(observer)));
+        id
+    }
+
+    pub(crate) fn notify_split(&mut self, left: &NodeRef<K>, separator: &K, right: &NodeRef<K>) {
+        let (left, right) = (left.borrow(), right.borrow());
+        self.watchers.observers.iter_mut()
+            .for_each(|(_, observer)| observer.on_split(&left.keys, separator, &right.keys));
+    }
+
+    pub(crate) fn notify_merge(&mut self, merged: &NodeRef<K>) {
+        let merged = merged.borrow();
+        self.watchers.observers.iter_mut()
+            .for_each(|(_, observer)| observer.on_merge(&merged.keys));
+    }
+
+    /// Report a rotation between the children `left` and `left + 1` of `parent`
+    pub(crate) fn notify_rotate(&mut self, parent: &NodeRef<K>, left: usize) {
+        if self.watchers.observers.is_empty() {
+            return;
+        }
+        let parent = parent.borrow();
+        let (left_node, right_node) = (parent.children[left].borrow(), parent.children[left + 1].borrow());
+        self.watchers.observers.iter_mut()
+            .for_each(|(_, observer)| observer.on_rotate(&left_node.keys, &parent.keys[left], &right_node.keys));
+    }
+
+    pub(crate) fn notify_root_change(&mut self) {
+        let height = self.height;
+        self.watchers.observers.iter_mut()
+            .for_each(|(_, observer)| observer.on_root_change(height));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Writes every callback down as a line
+    struct Log(Rc<RefCell<Vec<String>>>);
+
+    impl StructureObserver<u32> for Log {
+        fn on_split(&mut self, left: &[u32], separator: &u32, right: &[u32]) {
+            self.0.borrow_mut().push(format!("split {:?} {} {:?}", left, separator, right));
+        }
+
+        fn on_merge(&mut self, keys: &[u32]) {
+            self.0.borrow_mut().push(format!("merge {:?}", keys));
+        }
+
+        fn on_rotate(&mut self, left: &[u32], separator: &u32, right: &[u32]) {
+            self.0.borrow_mut().push(format!("rotate {:?} {} {:?}", left, separator, right));
+        }
+
+        fn on_root_change(&mut self, height: usize) {
+            self.0.borrow_mut().push(format!("root {}", height));
+        }
+    }
+
+    #[test]
+    fn structural_changes_are_reported() {
+        let mut tree = BTree::new(3);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let id = tree.observe(Log(Rc::clone(&log)));
+
+        tree.extend(1..=4);
+        let _ = tree.delete(&1);
+        let _ = tree.delete(&2);
+        // [2] over [1] [3, 4], then [3] over [2] [4], then a single [3, 4]
+        assert_eq!(*log.borrow(), vec![
+            "split [1] 2 [3]", "root 2",
+            "rotate [2] 3 [4]",
+            "merge [3, 4]", "root 1",
+        ].into_iter().map(String::from).collect::<Vec<_>>());
+        log.borrow_mut().clear();
+
+        assert!(tree.unsubscribe(id));
+        tree.clear();
+        assert!(log.borrow().is_empty());
+    }
+}
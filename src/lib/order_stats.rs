@@ -0,0 +1,187 @@
+use crate::range::{after_end, before_start};
+use crate::{BTree, KeySum};
+use std::ops::{Add, RangeBounds};
+use std::rc::Rc;
+
+impl<K: Ord> BTree<K> {
+    /// Number of keys inside `range`, found with two walks from the root using
+    /// the subtree sizes kept in every node rather than by visiting the keys
+    pub fn count_range<R: RangeBounds<K>>(&self, range: R) -> usize {
+        let not_after = self.count_while(|key| !after_end(range.end_bound(), key));
+        let before = self.count_while(|key| before_start(range.start_bound(), key));
+        not_after.saturating_sub(before)
+    }
+
+    /// Sum of the keys inside `range`. A tree built with `BTreeBuilder::key_sums`
+    /// adds up the sums kept for the subtrees that lie inside the range, so only
+    /// the paths to the ends of the range are visited. Other trees fall back to
+    /// adding up every key in range
+    pub fn sum_range<R: RangeBounds<K>>(&self, range: R) -> K
+        where K: Clone + Default + Add<Output = K> + 'static {
+        if self.summary::<KeySum>().is_some() {
+            return self.summarize_range::<KeySum, R>(range).unwrap_or_default();
+        }
+        self.range(range).fold(K::default(), |sum, key| sum + key)
+    }
+
+    /// Number of keys smaller than `value`, whether or not `value` is stored.
+    /// For a stored key this is its index in ascending order, see `select`
+    pub fn rank(&self, value: &K) -> usize {
+        self.count_while(|key| key < value)
+    }
+
+    /// A copy of the key at `index` in ascending order, the smallest key being
+    /// at 0. Subtree sizes let the walk from the root skip whole children, so
+    /// e.g. the median is `select(len() / 2)` without iterating
+    pub fn select(&self, index: usize) -> Option<K>
+        where K: Clone {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut node = Rc::clone(&self.root);
+        let mut index = index;
+        loop {
+            let child = {
+                let node_ref = node.borrow();
+                if node_ref.is_leaf() {
+                    return Some(node_ref.keys[index].clone());
+                }
+
+                // every child is followed by the key separating it from the next one
+                let mut found = None;
+                for (idx, child) in node_ref.children.iter().enumerate() {
+                    let size = child.borrow().size;
+                    if index < size {
+                        found = Some(Rc::clone(child));
+                        break;
+                    }
+                    index -= size;
+                    if index == 0 {
+                        return Some(node_ref.keys[idx].clone());
+                    }
+                    index -= 1;
+                }
+                found.expect("subtree sizes add up to the tree's length")
+            };
+            node = child;
+        }
+    }
+
+    /// Count the keys `pred` holds for, which have to come before all the others
+    fn count_while(&self, pred: impl Fn(&K) -> bool) -> usize {
+        let mut node = Rc::clone(&self.root);
+        let mut count = 0;
+
+        loop {
+            let child = {
+                let node_ref = node.borrow();
+                let idx = node_ref.keys.partition_point(&pred);
+                // the children left of the split hold nothing but matching keys
+                count += idx + node_ref.children.iter().take(idx)
+                    .map(|child| child.borrow().size)
+                    .sum::<usize>();
+                node_ref.children.get(idx).cloned()
+            };
+
+            match child {
+                Some(child) => node = child,
+                None => return count,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+    use std::ops::Bound;
+
+    #[test]
+    fn count_range_matches_a_scan() {
+        for order in [3, 4, 7] {
+            let mut tree = BTree::new(order);
+            for key in (0..300).map(|k| k * 37 % 301) {
+                let _ = tree.add(key * 2);
+            }
+            for key in (0..300).step_by(7) {
+                let _ = tree.delete(&(key * 2));
+            }
+
+            let bounds = [
+                (Bound::Included(10), Bound::Excluded(200)),
+                (Bound::Excluded(10), Bound::Included(200)),
+                (Bound::Included(11), Bound::Included(11)),
+                (Bound::Unbounded, Bound::Excluded(301)),
+                (Bound::Excluded(500), Bound::Unbounded),
+                (Bound::Included(400), Bound::Excluded(100)),
+            ];
+            for range in bounds {
+                assert_eq!(tree.count_range(range), tree.range(range).count(), "{:?}", range);
+            }
+            assert_eq!(tree.count_range(..), tree.len());
+        }
+    }
+
+    #[test]
+    fn sum_range_matches_a_scan() {
+        let mut summed = BTree::builder(5).key_sums().build();
+        let mut plain = BTree::new(5);
+        for key in (0..400_u64).map(|k| k * 37 % 401) {
+            let _ = summed.add(key);
+            let _ = plain.add(key);
+        }
+        summed.remove_range(100..150);
+        plain.remove_range(100..150);
+        for key in (0..400).step_by(9) {
+            let _ = summed.delete(&key);
+            let _ = plain.delete(&key);
+        }
+
+        let bounds = [
+            (Bound::Included(10), Bound::Excluded(300)),
+            (Bound::Excluded(99), Bound::Included(151)),
+            (Bound::Unbounded, Bound::Unbounded),
+            (Bound::Included(300), Bound::Excluded(10)),
+        ];
+        for range in bounds {
+            let scan = summed.range(range).sum::<u64>();
+            assert_eq!(summed.sum_range(range), scan, "{:?}", range);
+            assert_eq!(plain.sum_range(range), scan, "{:?}", range);
+        }
+        assert_eq!(BTree::<i64>::builder(3).key_sums().build().sum_range(..), 0);
+    }
+
+    #[test]
+    fn select_matches_iteration() {
+        for order in [3, 4, 7] {
+            let mut tree = BTree::new(order);
+            for key in (0..500).map(|k| k * 37 % 501) {
+                let _ = tree.add(key);
+            }
+            tree.remove_range(100..150);
+
+            for (idx, key) in tree.iter().enumerate() {
+                assert_eq!(tree.select(idx), Some(key));
+            }
+            assert_eq!(tree.select(tree.len()), None);
+        }
+        assert_eq!(BTree::<usize>::new(3).select(0), None);
+    }
+
+    #[test]
+    fn rank_is_the_inverse_of_select() {
+        let mut tree = BTree::new(4);
+        for key in (0..200).map(|k| k * 3) {
+            let _ = tree.add(key);
+        }
+
+        for idx in 0..tree.len() {
+            assert_eq!(tree.rank(&tree.select(idx).unwrap()), idx);
+        }
+        assert_eq!(tree.rank(&0), 0);
+        assert_eq!(tree.rank(&1), 1);
+        assert_eq!(tree.rank(&3), 1);
+        assert_eq!(tree.rank(&1000), 200);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::node::NodeRef;
+use crate::{BTree, PrefixKey};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::rc::Rc;
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Iterate over copies of the keys inside `range` in ascending order.
+    ///
+    /// The iterator starts by walking down to the lower bound, so only the keys
+    /// in range and the nodes on the way to them are visited. A range whose
+    /// start lies past its end is empty
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K> {
+        Range::new(&self.root, range.start_bound(), range.end_bound().cloned(), self.key_search)
+    }
+}
+
+impl<K: PrefixKey + Clone> BTree<K> {
+    /// Iterate over copies of the keys starting with `prefix` in ascending
+    /// order, the range from `prefix` up to its `prefix_successor`
+    pub fn iter_prefix(&self, prefix: &K) -> Range<'_, K> {
+        let end = prefix.prefix_successor().map_or(Bound::Unbounded, Bound::Excluded);
+        self.range((Bound::Included(prefix.clone()), end))
+    }
+}
+
+/// Locates a bound of type `Q` among the keys of a node, like `KeySearch`
+/// does for a key. A map's entries are searched by their key this way
+pub(crate) type BoundSearch<K, Q> = fn(&[K], &Q) -> Result<usize, usize>;
+
+/// Iterator over a range of keys, see `BTree::range`
+pub struct Range<'a, K, Q = K> {
+    /// The nodes from the root down to the current position, each with the
+    /// index of the next key to yield from it
+    stack: Vec<(NodeRef<K>, usize)>,
+    end: Bound<Q>,
+    key_search: BoundSearch<K, Q>,
+    tree: PhantomData<&'a BTree<K>>,
+}
+
+impl<K: Ord, Q> Range<'_, K, Q> {
+    /// Start at the first key of the tree under `root` at or after `start`
+    pub(crate) fn new(
+        root: &NodeRef<K>, start: Bound<&Q>, end: Bound<Q>, key_search: BoundSearch<K, Q>) -> Self {
+        let mut iter = Range { stack: Vec::new(), end, key_search, tree: PhantomData };
+        iter.seek(Rc::clone(root), start);
+        iter
+    }
+
+    /// Walk down from `node` to the first key at or after `start`
+    fn seek(&mut self, node: NodeRef<K>, start: Bound<&Q>) {
+        let key_search = self.key_search;
+        let mut node = node;
+
+        loop {
+            let (idx, descend) = {
+                let node_ref = node.borrow();
+                match start {
+                    Bound::Unbounded => (0, true),
+                    Bound::Included(key) => match key_search(&node_ref.keys, key) {
+                        Ok(idx) => (idx, false),
+                        Err(idx) => (idx, true),
+                    },
+                    Bound::Excluded(key) => match key_search(&node_ref.keys, key) {
+                        Ok(idx) => (idx + 1, true),
+                        Err(idx) => (idx, true),
+                    },
+                }
+            };
+
+            let child = if descend { node.borrow().try_clone_child(idx as isize) } else { None };
+            self.stack.push((node, idx));
+
+            match child {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+
+    fn past_end(&self, key: &K) -> bool {
+        // searching a lone key for the bound tells which side of it the key is on
+        let search = |end| (self.key_search)(std::slice::from_ref(key), end);
+        match &self.end {
+            Bound::Included(end) => search(end) == Err(0),
+            Bound::Excluded(end) => search(end) != Err(1),
+            Bound::Unbounded => false,
+        }
+    }
+
+    /// Step to the next key in range and hand back what `f` makes of it, so
+    /// only the parts that are needed are copied out of the node
+    pub(crate) fn next_with<T>(&mut self, f: impl FnOnce(&K) -> T) -> Option<T> {
+        loop {
+            let (node, idx) = self.stack.last()?;
+            let (node, idx) = (Rc::clone(node), *idx);
+            let node_ref = node.borrow();
+
+            if idx >= node_ref.keys.len() {
+                drop(node_ref);
+                self.stack.pop();
+                continue;
+            }
+
+            let key = &node_ref.keys[idx];
+            if self.past_end(key) {
+                self.stack.clear();
+                return None;
+            }
+            let item = f(key);
+
+            let next_child = node_ref.try_clone_child(idx as isize + 1);
+            drop(node_ref);
+            self.stack.last_mut().unwrap().1 += 1;
+
+            // the keys between this one and the next live under the child to its right
+            if let Some(child) = next_child {
+                self.seek(child, Bound::Unbounded);
+            }
+            return Some(item);
+        }
+    }
+}
+
+/// Whether `key` sorts before a range starting at `start`
+pub(crate) fn before_start<K: Ord>(start: Bound<&K>, key: &K) -> bool {
+    match start {
+        Bound::Included(start) => key < start,
+        Bound::Excluded(start) => key <= start,
+        Bound::Unbounded => false,
+    }
+}
+
+/// Whether `key` sorts after a range ending at `end`
+pub(crate) fn after_end<K: Ord>(end: Bound<&K>, key: &K) -> bool {
+    match end {
+        Bound::Included(end) => key > end,
+        Bound::Excluded(end) => key >= end,
+        Bound::Unbounded => false,
+    }
+}
+
+impl<K: Ord + Clone, Q> Iterator for Range<'_, K, Q> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.next_with(K::clone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+    use std::ops::Bound;
+
+    fn build_tree() -> BTree<usize> {
+        let mut tree = BTree::new(3);
+        for key in (0..100).map(|k| k * 37 % 101) {
+            let _ = tree.add(key * 2);
+        }
+        tree
+    }
+
+    #[test]
+    fn bounds_match_a_filtered_scan() {
+        let tree = build_tree();
+        let all: Vec<usize> = (0..101).map(|k| k * 2).filter(|k| *k != 64 * 2).collect();
+        assert_eq!(tree.range(..).collect::<Vec<_>>(), all);
+
+        let bounds = [
+            (Bound::Included(10), Bound::Excluded(20)),
+            (Bound::Excluded(10), Bound::Included(20)),
+            (Bound::Included(11), Bound::Included(21)),
+            (Bound::Excluded(127), Bound::Unbounded),
+            (Bound::Unbounded, Bound::Excluded(1)),
+            (Bound::Included(198), Bound::Included(500)),
+        ];
+        for range in bounds {
+            let expected: Vec<usize> = all.iter().copied()
+                .filter(|k| std::ops::RangeBounds::contains(&range, k))
+                .collect();
+            assert_eq!(tree.range(range).collect::<Vec<_>>(), expected, "{:?}", range);
+        }
+    }
+
+    #[test]
+    fn empty_ranges() {
+        let tree = build_tree();
+        assert_eq!(tree.range(500..).next(), None);
+        assert_eq!(tree.range(21..21).next(), None);
+        assert_eq!(tree.range((Bound::Excluded(20), Bound::Excluded(22))).next(), None);
+        assert_eq!(BTree::<usize>::new(3).range(..).next(), None);
+    }
+
+    #[test]
+    fn range_over_string_keys() {
+        let mut tree = BTree::new(4);
+        for word in ["pear", "apple", "fig", "kiwi", "plum", "date", "lime"] {
+            let _ = tree.add(String::from(word));
+        }
+
+        let words: Vec<String> = tree.range(String::from("d")..String::from("l")).collect();
+        assert_eq!(words, ["date", "fig", "kiwi"]);
+    }
+
+    #[test]
+    fn prefix_scans() {
+        let mut tree = BTree::new(3);
+        for word in ["pea", "pear", "peach", "pe", "pf", "apple", "p\u{10ffff}", "q"] {
+            let _ = tree.add(String::from(word));
+        }
+        let prefixed = |prefix: &str| tree.iter_prefix(&String::from(prefix)).collect::<Vec<_>>();
+        assert_eq!(prefixed("pea"), ["pea", "peach", "pear"]);
+        assert_eq!(prefixed("p"), ["pe", "pea", "peach", "pear", "pf", "p\u{10ffff}"]);
+        assert_eq!(prefixed("x"), Vec::<String>::new());
+        assert_eq!(prefixed("").len(), 8);
+
+        let mut bytes = BTree::new(4);
+        for key in [vec![1_u8, 0xff], vec![1, 0xff, 0], vec![2], vec![1, 0xfe]] {
+            let _ = bytes.add(key);
+        }
+        let prefixed: Vec<Vec<u8>> = bytes.iter_prefix(&vec![1, 0xff]).collect();
+        assert_eq!(prefixed, [vec![1, 0xff], vec![1, 0xff, 0]]);
+    }
+}
@@ -0,0 +1,94 @@
+//! A tree is serialized as its order and its keys in ascending order. Loading
+//! bulk builds a new tree from them, so parent links and child indexes are
+//! derived again instead of being trusted from the input. Subscribers, the
+//! changefeed, the memory budget and the search mode are not part of the data.
+
+use crate::{BTree, MAX_ORDER};
+use serde::de::Error;
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+impl<K: Ord + Serialize> Serialize for BTree<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tree = serializer.serialize_struct("BTree", 2)?;
+        tree.serialize_field("order", &self.order)?;
+        tree.serialize_field("keys", &Keys(self))?;
+        tree.end()
+    }
+}
+
+/// Writes the keys straight from the nodes, without copying them out first
+struct Keys<'a, K>(&'a BTree<K>);
+
+impl<K: Ord + Serialize> Serialize for Keys<'_, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        self.0.root.borrow().try_for_each_key(&mut |key| seq.serialize_element(key))?;
+        seq.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "BTree")]
+struct TreeData<K> {
+    order: usize,
+    keys: Vec<K>,
+}
+
+impl<'de, K: Ord + Deserialize<'de>> Deserialize<'de> for BTree<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = TreeData::<K>::deserialize(deserializer)?;
+
+        if data.order < 3 {
+            return Err(D::Error::custom(format!("order must be at least 3, got {}", data.order)));
+        }
+        if data.order > MAX_ORDER {
+            return Err(D::Error::custom(format!("order must be at most {}, got {}", MAX_ORDER, data.order)));
+        }
+        if data.keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(D::Error::custom("keys must be sorted and unique"));
+        }
+
+        Ok(BTree::from_sorted_keys(data.order, data.keys.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+
+    #[test]
+    fn json_round_trip() {
+        let mut tree = BTree::new(4);
+        for key in (0..100).rev() {
+            let _ = tree.add(key * 3);
+        }
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert!(json.starts_with(r#"{"order":4,"keys":[0,3,6,"#));
+
+        let back: BTree<usize> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, tree);
+        assert_eq!(back.order(), 4);
+        assert!(back.inspect().is_valid());
+    }
+
+    #[test]
+    fn invalid_data_is_rejected() {
+        let unsorted = serde_json::from_str::<BTree<usize>>(r#"{"order":4,"keys":[1,3,2]}"#);
+        assert!(unsorted.err().unwrap().to_string().contains("sorted and unique"));
+
+        let duplicated = serde_json::from_str::<BTree<usize>>(r#"{"order":4,"keys":[1,1]}"#);
+        assert!(duplicated.is_err());
+
+        let small_order = serde_json::from_str::<BTree<usize>>(r#"{"order":2,"keys":[]}"#);
+        assert!(small_order.is_err());
+
+        let huge_order = serde_json::from_str::<BTree<usize>>(r#"{"order":1152921504606846976,"keys":[1]}"#);
+        assert!(huge_order.err().unwrap().to_string().contains("at most"));
+
+        let strings: BTree<String> =
+            serde_json::from_str(r#"{"order":3,"keys":["a","b"]}"#).unwrap();
+        assert!(strings.contains(&String::from("b")));
+    }
+}
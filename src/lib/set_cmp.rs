@@ -0,0 +1,128 @@
+use crate::{BTree, Change, ChangeKind, Range};
+use std::cmp::Ordering;
+use std::iter::Peekable;
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Whether every key of this tree is also in `other`. Both trees are
+    /// walked in order side by side, stopping at the first key `other` lacks
+    pub fn is_subset(&self, other: &BTree<K>) -> bool {
+        if self.len() > other.len() {
+            return false;
+        }
+
+        let mut theirs = other.iter();
+        self.iter().all(|key| theirs.by_ref().find(|their| *their >= key) == Some(key))
+    }
+
+    /// Whether every key of `other` is also in this tree, see `is_subset`
+    pub fn is_superset(&self, other: &BTree<K>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Whether the trees have no key in common. Both trees are walked in
+    /// order side by side, stopping at the first shared key
+    pub fn is_disjoint(&self, other: &BTree<K>) -> bool {
+        let mut ours = self.iter();
+        let mut theirs = other.iter();
+        let (mut our_key, mut their_key) = (ours.next(), theirs.next());
+
+        while let (Some(ours_now), Some(theirs_now)) = (&our_key, &their_key) {
+            match ours_now.cmp(theirs_now) {
+                Ordering::Less => our_key = ours.next(),
+                Ordering::Greater => their_key = theirs.next(),
+                Ordering::Equal => return false,
+            }
+        }
+        true
+    }
+
+    /// The changes that turn this tree into `other`, in key order: an insert
+    /// for every key only `other` holds and a delete for every key only this
+    /// tree holds. Trees never share nodes, so both are walked in full
+    pub fn diff<'a>(&'a self, other: &'a BTree<K>) -> Diff<'a, K> {
+        Diff { ours: self.iter().peekable(), theirs: other.iter().peekable() }
+    }
+}
+
+/// Iterator over the changes between two trees, see `BTree::diff`
+pub struct Diff<'a, K: Ord + Clone> {
+    ours: Peekable<Range<'a, K>>,
+    theirs: Peekable<Range<'a, K>>,
+}
+
+impl<K: Ord + Clone> Iterator for Diff<'_, K> {
+    type Item = Change<K>;
+
+    fn next(&mut self) -> Option<Change<K>> {
+        loop {
+            let order = match (self.ours.peek(), self.theirs.peek()) {
+                (Some(ours), Some(theirs)) => ours.cmp(theirs),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+
+            let (key, kind) = match order {
+                Ordering::Less => (self.ours.next(), ChangeKind::Deleted),
+                Ordering::Greater => (self.theirs.next(), ChangeKind::Inserted),
+                Ordering::Equal => {
+                    self.ours.next();
+                    self.theirs.next();
+                    continue;
+                }
+            };
+            return key.map(|key| Change { key, kind });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BTree, ChangeKind};
+
+    #[test]
+    fn containment_between_trees() {
+        let evens: BTree<u32> = (0..100).step_by(2).collect();
+        let fours: BTree<u32> = (0..100).step_by(4).collect();
+        let odds: BTree<u32> = (1..100).step_by(2).collect();
+        let empty = BTree::new(3);
+
+        assert!(fours.is_subset(&evens) && evens.is_superset(&fours));
+        assert!(!evens.is_subset(&fours) && !fours.is_superset(&evens));
+        assert!(evens.is_subset(&evens) && evens.is_superset(&evens));
+        assert!(empty.is_subset(&odds) && !odds.is_subset(&empty));
+        assert!(!BTree::from([2, 101]).is_subset(&evens));
+
+        assert!(evens.is_disjoint(&odds) && odds.is_disjoint(&fours));
+        assert!(!evens.is_disjoint(&fours));
+        assert!(!odds.is_disjoint(&BTree::from([50, 99])));
+        assert!(empty.is_disjoint(&evens) && empty.is_disjoint(&empty));
+    }
+
+    #[test]
+    fn diff_turns_one_tree_into_the_other() {
+        let old = BTree::from([1, 2, 3, 5, 8, 13]);
+        let mut new = old.clone();
+        let _ = new.delete(&1);
+        let _ = new.delete(&8);
+        new.extend([4, 21, 34]);
+
+        let changes: Vec<(u32, ChangeKind)> = old.diff(&new)
+            .map(|change| (change.key, change.kind))
+            .collect();
+        assert_eq!(changes, vec![
+            (1, ChangeKind::Deleted), (4, ChangeKind::Inserted), (8, ChangeKind::Deleted),
+            (21, ChangeKind::Inserted), (34, ChangeKind::Inserted),
+        ]);
+
+        let mut synced = old.clone();
+        for change in old.diff(&new) {
+            match change.kind {
+                ChangeKind::Inserted => assert!(synced.add(change.key).is_ok()),
+                _ => assert!(synced.delete(&change.key).is_ok()),
+            }
+        }
+        assert_eq!(synced, new);
+        assert_eq!(new.diff(&synced).count(), 0);
+    }
+}
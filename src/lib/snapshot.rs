@@ -0,0 +1,239 @@
+//! Compact binary snapshot of a tree that keeps its node layout, so restoring
+//! it needs no searching, splitting or sorting.
+//!
+//! Keys are encoded with `BinaryKey` and all other integers are little endian:
+//! ```text
+//! header:  magic "BTSN", version u32, order u64, key count u64, height u64
+//! nodes:   pre-order from the root, each node is a key count u32 followed by
+//!          its encoded keys and then, above the leaf level, its children
+//! ```
+
+use crate::dump::invalid_data;
+use crate::node::{node_utils::new_node_ref, NodeRef};
+use crate::{BTree, BinaryKey, MAX_ORDER};
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+
+const MAGIC: &[u8; 4] = b"BTSN";
+const VERSION: u32 = 1;
+
+impl<K: Ord + Clone + BinaryKey> BTree<K> {
+    /// Write a snapshot of the tree, nodes and all, readable by `read_from`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        (self.order as u64).write_key(&mut writer)?;
+        (self.key_count as u64).write_key(&mut writer)?;
+        (self.height as u64).write_key(&mut writer)?;
+
+        write_node(&self.root, &mut writer)
+    }
+
+    /// Restore a tree written by `write_to`. The snapshot is checked against
+    /// the B-tree rules, a corrupt or hand edited one fails with `InvalidData`
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<BTree<K>> {
+        let mut magic = [0_u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a tree snapshot"));
+        }
+
+        let version = u32::read_key(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data(format!("unsupported snapshot version {}", version)));
+        }
+
+        let order = usize::try_from(u64::read_key(&mut reader)?).unwrap_or(usize::MAX);
+        let key_count = u64::read_key(&mut reader)? as usize;
+        let height = u64::read_key(&mut reader)? as usize;
+        // nodes are read recursively, a height no tree of that many keys can
+        // reach must not be followed
+        if !(3..=MAX_ORDER).contains(&order) || height == 0 || height > max_height(order, key_count) {
+            return Err(invalid_data("snapshot header is corrupt"));
+        }
+
+        let mut tree = BTree::new(order);
+        let mut snapshot = SnapshotReader { reader, order, height, node_count: 0, key_count: 0 };
+        tree.root = snapshot.read_node(0)?;
+        tree.node_count = snapshot.node_count;
+        tree.key_count = snapshot.key_count;
+        tree.height = height;
+
+        if tree.key_count != key_count || !tree.inspect().is_valid() {
+            return Err(invalid_data("snapshot does not hold a valid tree"));
+        }
+
+        Ok(tree)
+    }
+}
+
+/// Height of the tallest valid tree of `order` holding `key_count` keys. A tree
+/// of height h has at least `2 * ceil(order/2)^(h-1) - 1` keys
+fn max_height(order: usize, key_count: usize) -> usize {
+    let min_children = order.div_ceil(2);
+    let (mut height, mut min_keys) = (1, 1_usize);
+    loop {
+        let taller = min_keys.checked_mul(min_children).and_then(|keys| keys.checked_add(min_children - 1));
+        match taller {
+            Some(taller) if taller <= key_count => {
+                height += 1;
+                min_keys = taller;
+            }
+            _ => return height,
+        }
+    }
+}
+
+fn write_node<K: Ord + BinaryKey, W: Write>(node: &NodeRef<K>, writer: &mut W) -> io::Result<()> {
+    let node = node.borrow();
+    writer.write_all(&(node.keys.len() as u32).to_le_bytes())?;
+    for key in node.keys.iter() {
+        key.write_key(writer)?;
+    }
+
+    node.children.iter().try_for_each(|child| write_node(child, writer))
+}
+
+struct SnapshotReader<R: Read> {
+    reader: R,
+    order: usize,
+    height: usize,
+    node_count: usize,
+    key_count: usize,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Read the node at `depth` and everything below it
+    fn read_node<K: Ord + BinaryKey>(&mut self, depth: usize) -> io::Result<NodeRef<K>> {
+        let key_count = u32::read_key(&mut self.reader)? as usize;
+        if key_count >= self.order {
+            return Err(invalid_data(
+                format!("node with {} keys in a tree of order {}", key_count, self.order)));
+        }
+
+        let node = new_node_ref(self.order);
+        self.node_count += 1;
+        self.key_count += key_count;
+
+        for _ in 0..key_count {
+            let key = K::read_key(&mut self.reader)?;
+            node.borrow_mut().keys.push(key);
+        }
+
+        if depth + 1 < self.height {
+            for idx in 0..=key_count {
+                let child = self.read_node(depth + 1)?;
+                {
+                    let mut child_ref = child.borrow_mut();
+                    child_ref.parent = Rc::downgrade(&node);
+                    child_ref.index_in_parent = Some(idx);
+                }
+                node.borrow_mut().children.push(child);
+            }
+        }
+
+        node.borrow_mut().recount();
+        Ok(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BTree, MAX_ORDER};
+    use std::io::ErrorKind;
+
+    fn build_tree() -> BTree<usize> {
+        let mut tree = BTree::new(4);
+        for key in (0..300).map(|k| k * 37 % 301) {
+            let _ = tree.add(key);
+        }
+        tree
+    }
+
+    #[test]
+    fn snapshot_keeps_the_layout() {
+        let tree = build_tree();
+        let mut out = Vec::new();
+        tree.write_to(&mut out).unwrap();
+
+        assert_eq!(&out[..4], b"BTSN");
+        let restored = BTree::<usize>::read_from(out.as_slice()).unwrap();
+
+        assert_eq!(restored, tree);
+        assert_eq!(restored.key_counts_by_level(), tree.key_counts_by_level());
+        assert_eq!((restored.order(), restored.height()), (4, tree.height()));
+        assert_eq!(restored.memory_usage(), tree.memory_usage());
+        assert!(restored.inspect().is_valid());
+    }
+
+    #[test]
+    fn empty_and_string_trees() {
+        let mut out = Vec::new();
+        BTree::<usize>::new(3).write_to(&mut out).unwrap();
+        assert!(BTree::<usize>::read_from(out.as_slice()).unwrap().is_empty());
+
+        let tree: BTree<String> =
+            ["fig", "pear", "apple", "kiwi"].into_iter().map(String::from).collect();
+        let mut out = Vec::new();
+        tree.write_to(&mut out).unwrap();
+        assert_eq!(BTree::<String>::read_from(out.as_slice()).unwrap(), tree);
+    }
+
+    #[test]
+    fn corrupt_snapshots_are_rejected() {
+        let mut out = Vec::new();
+        build_tree().write_to(&mut out).unwrap();
+
+        let err = BTree::<usize>::read_from(&out[..out.len() - 4]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let mut bad_version = out.clone();
+        bad_version[4] = 9;
+        let err = BTree::<usize>::read_from(bad_version.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // swap the root's first key for one larger than everything in the tree
+        let mut bad_key = out.clone();
+        bad_key[36..44].copy_from_slice(&1000_u64.to_le_bytes());
+        let err = BTree::<usize>::read_from(bad_key.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // an empty tree claiming to be u64::MAX levels high, over 2M empty
+        // nodes, used to overflow the stack before the height was checked
+        let mut too_high = b"BTSN".to_vec();
+        too_high.extend(1_u32.to_le_bytes());
+        for field in [3, 0, u64::MAX] {
+            too_high.extend(field.to_le_bytes());
+        }
+        too_high.resize(too_high.len() + 2_000_000 * 4, 0);
+        let err = BTree::<usize>::read_from(too_high.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // an order this large used to overflow the capacity of the root's Vecs
+        for order in [MAX_ORDER as u64 + 1, 1 << 60, u64::MAX] {
+            let mut too_wide = out.clone();
+            too_wide[8..16].copy_from_slice(&order.to_le_bytes());
+            let err = BTree::<usize>::read_from(too_wide.as_slice()).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn height_is_bounded_by_the_key_count() {
+        assert_eq!(super::max_height(3, 0), 1);
+        assert_eq!(super::max_height(3, 2), 1);
+        assert_eq!(super::max_height(3, 3), 2);
+        assert_eq!(super::max_height(3, 7), 3);
+        assert_eq!(super::max_height(5, 17), 3);
+        assert_eq!(super::max_height(3, usize::MAX), 64);
+
+        for order in 3..8 {
+            let mut tree = BTree::new(order);
+            for key in 0..500 {
+                let _ = tree.delete(&(key / 2));
+                let _ = tree.add(key);
+                assert!(tree.height() <= super::max_height(order, tree.len()));
+            }
+        }
+    }
+}
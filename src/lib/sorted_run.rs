@@ -0,0 +1,291 @@
+//! Immutable sorted run export, laid out like an SSTable so LSM style tools can
+//! merge tree snapshots without loading them back into a tree.
+//!
+//! Keys are encoded with `BinaryKey` and all other integers are little endian:
+//! ```text
+//! header:  magic "BTSR", version u32
+//! data:    blocks of up to SORTED_RUN_BLOCK_KEYS keys, each a key count u32
+//!          followed by the encoded keys, ascending
+//! index:   entry count u64, then the first key and offset u64 of every block
+//! bloom:   bit count u64, hash count u32, then the bits packed into bytes
+//! footer:  index offset u64, bloom offset u64, key count u64, magic "BTSR", version u32
+//! ```
+//! The bloom filter sets bit `(h1 + i * h2) % bit count` for `i` in `0..hash count`,
+//! with `h1 = mix(fnv1a(encoded key))`, `h2 = mix(h1) | 1`, `fnv1a` being 64 bit
+//! FNV-1a and `mix` the SplitMix64 finalizer.
+
+use crate::{BTree, BinaryKey};
+use std::io::{self, Write};
+use std::mem;
+
+/// Keys stored in each data block
+pub const SORTED_RUN_BLOCK_KEYS: usize = 128;
+
+const MAGIC: &[u8; 4] = b"BTSR";
+const VERSION: u32 = 1;
+const BLOOM_BITS_PER_KEY: usize = 10;
+const BLOOM_HASHES: u32 = 7;
+
+impl<K: Ord + BinaryKey> BTree<K> {
+    /// Write every key as a sorted run with a sparse block index and a bloom filter
+    pub fn write_sorted_run<W: Write>(&self, writer: W) -> io::Result<()> {
+        let root = self.root.borrow();
+        let key_count = self.len();
+
+        let mut writer = CountingWriter { inner: writer, written: 0 };
+        let mut bloom = BloomFilter::new(key_count);
+        let mut index: Vec<(Vec<u8>, u64)> =
+            Vec::with_capacity(key_count.div_ceil(SORTED_RUN_BLOCK_KEYS));
+        let mut block = Block::default();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        root.try_for_each_key(&mut |key| -> io::Result<()> {
+            let start = block.keys.len();
+            key.write_key(&mut block.keys)?;
+            let encoded = &block.keys[start..];
+
+            bloom.insert(encoded);
+            if block.key_count == 0 {
+                block.first_key = encoded.to_vec();
+            }
+            block.key_count += 1;
+
+            if block.key_count == SORTED_RUN_BLOCK_KEYS {
+                index.push(block.write(&mut writer)?);
+            }
+            Ok(())
+        })?;
+
+        if block.key_count > 0 {
+            index.push(block.write(&mut writer)?);
+        }
+
+        let index_offset = writer.written;
+        writer.write_all(&(index.len() as u64).to_le_bytes())?;
+        for (first_key, offset) in index {
+            writer.write_all(&first_key)?;
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+
+        let bloom_offset = writer.written;
+        writer.write_all(&(bloom.bit_count as u64).to_le_bytes())?;
+        writer.write_all(&BLOOM_HASHES.to_le_bytes())?;
+        writer.write_all(&bloom.bits)?;
+
+        writer.write_all(&index_offset.to_le_bytes())?;
+        writer.write_all(&bloom_offset.to_le_bytes())?;
+        writer.write_all(&(key_count as u64).to_le_bytes())?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())
+    }
+}
+
+/// Encoded keys waiting to be written as a data block
+#[derive(Default)]
+struct Block {
+    keys: Vec<u8>,
+    key_count: usize,
+    first_key: Vec<u8>,
+}
+
+impl Block {
+    /// Write the block and empty it for the next one
+    ///
+    /// # Returns
+    /// The block's index entry, its first key and offset
+    fn write<W: Write>(&mut self, writer: &mut CountingWriter<W>) -> io::Result<(Vec<u8>, u64)> {
+        let offset = writer.written;
+        writer.write_all(&(self.key_count as u32).to_le_bytes())?;
+        writer.write_all(&self.keys)?;
+
+        self.keys.clear();
+        self.key_count = 0;
+        Ok((mem::take(&mut self.first_key), offset))
+    }
+}
+
+/// Keeps track of the offset each section starts at
+struct CountingWriter<W: Write> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct BloomFilter {
+    bits: Vec<u8>,
+    bit_count: usize,
+}
+
+impl BloomFilter {
+    fn new(key_count: usize) -> Self {
+        let bit_count = (key_count * BLOOM_BITS_PER_KEY).max(64);
+        Self { bits: vec![0; bit_count.div_ceil(8)], bit_count }
+    }
+
+    fn insert(&mut self, encoded_key: &[u8]) {
+        for bit in bloom_bits(encoded_key, self.bit_count) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+}
+
+fn bloom_bits(encoded_key: &[u8], bit_count: usize) -> impl Iterator<Item = usize> {
+    let h1 = mix(fnv1a(encoded_key));
+    let h2 = mix(h1) | 1;
+    (0..BLOOM_HASHES as u64)
+        .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count as u64) as usize)
+}
+
+/// 64 bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// SplitMix64 finalizer
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER_LEN: usize = 8;
+    const FOOTER_LEN: usize = 32;
+
+    fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Minimal reader following the documented layout
+    struct Run<'a> {
+        bytes: &'a [u8],
+        index: Vec<(u64, usize)>,
+        bloom_offset: usize,
+        key_count: u64,
+    }
+
+    impl<'a> Run<'a> {
+        fn parse(bytes: &'a [u8]) -> Self {
+            assert_eq!(&bytes[..4], MAGIC);
+            assert_eq!(read_u32(bytes, 4), VERSION);
+
+            let footer = bytes.len() - FOOTER_LEN;
+            assert_eq!(&bytes[footer + 24..footer + 28], MAGIC);
+
+            let index_offset = read_u64(bytes, footer) as usize;
+            let index = (0..read_u64(bytes, index_offset) as usize)
+                .map(|idx| {
+                    let entry = index_offset + 8 + idx * 16;
+                    (read_u64(bytes, entry), read_u64(bytes, entry + 8) as usize)
+                })
+                .collect();
+
+            Run {
+                bytes,
+                index,
+                bloom_offset: read_u64(bytes, footer + 8) as usize,
+                key_count: read_u64(bytes, footer + 16),
+            }
+        }
+
+        fn might_contain(&self, key: u64) -> bool {
+            let bit_count = read_u64(self.bytes, self.bloom_offset) as usize;
+            let bits = &self.bytes[self.bloom_offset + 12..];
+            bloom_bits(&key.to_le_bytes(), bit_count)
+                .all(|bit| bits[bit / 8] & (1 << (bit % 8)) != 0)
+        }
+
+        fn block(&self, offset: usize) -> Vec<u64> {
+            (0..read_u32(self.bytes, offset) as usize)
+                .map(|idx| read_u64(self.bytes, offset + 4 + idx * 8))
+                .collect()
+        }
+
+        fn contains(&self, key: u64) -> bool {
+            let block_idx = self.index.partition_point(|(first_key, _)| *first_key <= key);
+            block_idx > 0 && self.block(self.index[block_idx - 1].1).binary_search(&key).is_ok()
+        }
+    }
+
+    #[test]
+    fn run_holds_every_key_in_order() {
+        let tree = BTree::from_slice(&(0..1000).map(|k| k * 3).collect::<Vec<usize>>());
+        let mut out = Vec::new();
+        tree.write_sorted_run(&mut out).unwrap();
+
+        let run = Run::parse(&out);
+        assert_eq!(run.key_count, 1000);
+        assert_eq!(run.index.len(), 1000_usize.div_ceil(SORTED_RUN_BLOCK_KEYS));
+        assert_eq!(run.index[0].1, HEADER_LEN);
+
+        let keys: Vec<u64> = run.index.iter().flat_map(|(_, offset)| run.block(*offset)).collect();
+        assert_eq!(keys, (0..1000).map(|k| k * 3).collect::<Vec<u64>>());
+
+        for key in 0..3000 {
+            assert_eq!(run.contains(key), key % 3 == 0);
+        }
+    }
+
+    #[test]
+    fn bloom_filter_has_no_false_negatives() {
+        let tree = BTree::from_slice(&(0..500).map(|k| k * 7).collect::<Vec<usize>>());
+        let mut out = Vec::new();
+        tree.write_sorted_run(&mut out).unwrap();
+        let run = Run::parse(&out);
+
+        assert!((0..500).all(|k| run.might_contain(k * 7)));
+
+        let false_positives = (0..3500).filter(|k| k % 7 != 0 && run.might_contain(*k)).count();
+        assert!(false_positives < 3000 / 20, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn empty_tree_gives_empty_run() {
+        let mut out = Vec::new();
+        BTree::<usize>::new(3).write_sorted_run(&mut out).unwrap();
+
+        let run = Run::parse(&out);
+        assert_eq!(run.key_count, 0);
+        assert!(run.index.is_empty());
+        assert!(!run.contains(0));
+    }
+
+    #[test]
+    fn index_holds_encoded_first_keys() {
+        let keys: Vec<String> = (0..200).map(|k| format!("key{:03}", k)).collect();
+        let mut out = Vec::new();
+        BTree::from_slice(&keys).write_sorted_run(&mut out).unwrap();
+
+        let footer = out.len() - FOOTER_LEN;
+        assert_eq!(read_u64(&out, footer + 16), 200);
+
+        // each index entry is a u64 length, the 6 key bytes and the block offset
+        let index_offset = read_u64(&out, footer) as usize;
+        assert_eq!(read_u64(&out, index_offset), 2);
+        let second_entry = index_offset + 8 + (8 + 6 + 8);
+        assert_eq!(read_u64(&out, second_entry), 6);
+        assert_eq!(&out[second_entry + 8..second_entry + 14], b"key128");
+    }
+}
@@ -0,0 +1,368 @@
+use crate::changefeed::Changefeed;
+use crate::node::node_utils::{edge_leaf, recount_to_root, relink_children};
+use crate::node::NodeRef;
+use crate::watch::Watchers;
+use crate::{BTree, Change, ChangeKind, Metrics};
+use std::mem;
+use std::rc::Rc;
+
+impl<K: Ord + Clone> BTree<K> {
+    /// Move every key from `key` up into a new tree, keeping the smaller ones.
+    ///
+    /// Each node on the search path for `key` is cut in two, the right halves
+    /// being linked into the new tree, and the nodes left short along the two
+    /// cuts are fixed with a few rotations and merges per level, so it is
+    /// O(log n). Every node keeps the count of the nodes below it, which gives
+    /// the new tree's without visiting them. The new tree has the same order
+    /// and settings, with an empty changefeed and no subscribers. Subscribers
+    /// and the changefeed of this tree see a delete for every key moved out
+    pub fn split_off(&mut self, key: &K) -> BTree<K> {
+        let mut right = self.empty_like();
+        let mut left_path = Vec::new();
+        let mut right_path: Vec<NodeRef<K>> = Vec::new();
+
+        let mut node = Rc::clone(&self.root);
+        loop {
+            let right_node = self.new_node();
+            let (keys, children, child) = {
+                let mut node_ref = node.borrow_mut();
+                let idx = node_ref.keys.partition_point(|stored| stored < key);
+                let keys = node_ref.keys.split_off(idx);
+
+                // the child at `idx` is cut a level down, its right half
+                // becomes the first child of the right node
+                let children = if node_ref.is_leaf() { Vec::new() } else { node_ref.children.split_off(idx + 1) };
+                (keys, children, node_ref.children.get(idx).cloned())
+            };
+            {
+                let mut right_ref = right_node.borrow_mut();
+                right_ref.keys.extend(keys);
+                right_ref.children.extend(children);
+            }
+
+            match right_path.last() {
+                Some(parent) => {
+                    right_node.borrow_mut().parent = Rc::downgrade(parent);
+                    parent.borrow_mut().children.insert(0, Rc::clone(&right_node));
+                }
+                None => right.root = Rc::clone(&right_node),
+            }
+            left_path.push(node);
+            right_path.push(right_node);
+
+            match child {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+
+        // links the children moved into the right nodes
+        right_path.iter().for_each(relink_children);
+        recount_to_root(left_path.last().unwrap());
+        recount_to_root(right_path.last().unwrap());
+        #[cfg(feature = "merkle")]
+        left_path.last().unwrap().borrow().invalidate_hash();
+
+        right.key_count = right.root.borrow().size;
+        right.node_count = right.root.borrow().node_count;
+        right.height = self.height;
+        self.key_count -= right.key_count;
+        self.node_count = self.node_count + right_path.len() - right.node_count;
+
+        self.fix_short_nodes(&left_path);
+        right.fix_short_nodes(&right_path);
+        self.run_invariant_check();
+        right.run_invariant_check();
+
+        if self.is_observed() {
+            for key in right.iter() {
+                self.record_change(Change { key, kind: ChangeKind::Deleted });
+            }
+        }
+        right
+    }
+
+    /// Keep the first `n` keys and move the rest into a new tree, like
+    /// `split_off` at the key of rank `n`. That key is found with `select`
+    /// from the subtree sizes, so the split stays O(log n). Everything is kept
+    /// when the tree has `n` keys or fewer
+    pub fn split_at_rank(&mut self, n: usize) -> BTree<K> {
+        match self.select(n) {
+            Some(key) => self.split_off(&key),
+            None => self.empty_like(),
+        }
+    }
+
+    /// Move every key of `other` into this tree.
+    ///
+    /// When all of `other`'s keys sort after this tree's, and both trees have
+    /// the same order and augment, the shorter tree is hung off the spine of
+    /// the taller one at the level where their heights match, with `other`'s
+    /// smallest key between them, which is O(log n). Otherwise, or when
+    /// `other`'s nodes would go over the memory budget, the keys are added one
+    /// by one like `extend` and the ones past the budget are dropped.
+    /// Subscribers and the changefeed see an insert for every key added
+    pub fn append(&mut self, mut other: BTree<K>) {
+        if other.is_empty() {
+            return;
+        }
+        // the joined tree keeps the nodes of both, plus up to one per level from splits
+        let joined_nodes = self.node_count + other.node_count + self.height.max(other.height);
+        if !self.can_join(&other) || self.check_rebuilt_budget(self.order, joined_nodes).is_err() {
+            self.extend(other);
+            return;
+        }
+        let appended: Vec<K> = if self.is_observed() { other.iter().collect() } else { Vec::new() };
+
+        if self.is_empty() {
+            self.root = other.root;
+            self.node_count = other.node_count;
+            self.key_count = other.key_count;
+            self.height = other.height;
+            self.notify_root_change();
+        } else {
+            let leaf = edge_leaf(Rc::clone(&other.root), true);
+            let (separator, _) = other.remove_at(leaf, 0);
+            self.join(separator, other);
+        }
+
+        self.run_invariant_check();
+        for key in appended {
+            self.record_change(Change { key, kind: ChangeKind::Inserted });
+        }
+    }
+
+    /// Whether the non empty `other` can be hung off this tree as it is
+    fn can_join(&self, other: &BTree<K>) -> bool {
+        let augment = |tree: &BTree<K>| tree.root.borrow().summary.as_ref().map(|summary| summary.augment());
+        if other.order != self.order || augment(other) != augment(self) {
+            return false;
+        }
+
+        let last = edge_leaf(Rc::clone(&self.root), false);
+        let first = edge_leaf(Rc::clone(&other.root), true);
+        let last = last.borrow();
+        let first = first.borrow();
+        last.keys.last().is_none_or(|last| *last < first.keys[0])
+    }
+
+    /// Join a non empty tree and `right`, with `separator` sorting after this
+    /// tree's keys and before `right`'s
+    fn join(&mut self, separator: K, right: BTree<K>) {
+        let (left_height, right_height) = (self.height, right.height);
+        let root_changes = left_height <= right_height;
+        self.node_count += right.node_count;
+        self.key_count += right.key_count + 1;
+        self.height = left_height.max(right_height);
+
+        // the node on the spine of the taller tree whose children are as high
+        // as the root of the shorter one, which becomes its edge child. Trees
+        // of the same height get a new root over both of theirs
+        let (parent, attached) = if left_height == right_height {
+            let root = self.new_node();
+            self.node_count += 1;
+            self.height += 1;
+            let left = mem::replace(&mut self.root, Rc::clone(&root));
+            let mut root_ref = root.borrow_mut();
+            root_ref.keys.push(separator);
+            root_ref.children = vec![Rc::clone(&left), Rc::clone(&right.root)];
+            drop(root_ref);
+            (root, vec![left, right.root])
+        } else if left_height > right_height {
+            let parent = spine_node(&self.root, left_height - right_height - 1, false);
+            let mut parent_ref = parent.borrow_mut();
+            parent_ref.keys.push(separator);
+            parent_ref.children.push(Rc::clone(&right.root));
+            drop(parent_ref);
+            (parent, vec![right.root])
+        } else {
+            let parent = spine_node(&right.root, right_height - left_height - 1, true);
+            let left = mem::replace(&mut self.root, right.root);
+            let mut parent_ref = parent.borrow_mut();
+            parent_ref.keys.insert(0, separator);
+            parent_ref.children.insert(0, Rc::clone(&left));
+            drop(parent_ref);
+            (parent, vec![left])
+        };
+
+        relink_children(&parent);
+        recount_to_root(&parent);
+        #[cfg(feature = "merkle")]
+        parent.borrow().invalidate_hash();
+
+        // the separator can overflow the node it joined, and the roots hung
+        // below it may have fewer keys than a node below the root needs
+        if parent.borrow().is_key_overflowing() {
+            self.split_if_full(parent, 0);
+        }
+        if root_changes {
+            self.notify_root_change();
+        }
+        self.fix_short_nodes(&attached);
+    }
+
+    /// An empty tree with the same order and settings, without the subscribers
+    fn empty_like(&self) -> BTree<K> {
+        BTree {
+            root: self.new_node(),
+            order: self.order,
+            node_count: 1,
+            key_count: 0,
+            height: 1,
+            memory_budget: self.memory_budget,
+            key_search: self.key_search,
+            watchers: Watchers::default(),
+            changefeed: self.changefeed.as_ref().map(|_| Changefeed::default()),
+            invariant_check: self.invariant_check,
+            augment: self.augment,
+            metrics: Metrics::default(),
+            events: None,
+            pack_appends: self.pack_appends,
+        }
+    }
+}
+
+/// The node `depth` levels down the first or the last children of `node`
+fn spine_node<K>(node: &NodeRef<K>, depth: usize, first: bool) -> NodeRef<K> {
+    let mut node = Rc::clone(node);
+    for _ in 0..depth {
+        let child = {
+            let node_ref = node.borrow();
+            let child = if first { node_ref.children.first() } else { node_ref.children.last() };
+            Rc::clone(child.unwrap())
+        };
+        node = child;
+    }
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BTree, ChangeKind};
+
+    #[test]
+    fn split_off_matches_a_partition() {
+        let split_keys = [0, 1, 2, 57, 75, 114, 149, 150, 151, 400];
+        for order in 3..8 {
+            for split_key in split_keys {
+                let mut tree = BTree::builder(order).check_invariants().build();
+                // every key up to 150 but 114
+                for key in (0..150).map(|k| k * 37 % 151) {
+                    let _ = tree.add(key);
+                }
+                let (kept, moved): (Vec<usize>, Vec<usize>) =
+                    (0..=150).filter(|k| *k != 114).partition(|k| *k < split_key);
+
+                let right = tree.split_off(&split_key);
+                let msg = format!("order {} split at {}", order, split_key);
+                assert_eq!(tree.iter().collect::<Vec<_>>(), kept, "{}", msg);
+                assert_eq!(right.iter().collect::<Vec<_>>(), moved, "{}", msg);
+                assert_eq!(right.order(), order);
+            }
+        }
+    }
+
+    #[test]
+    fn split_halves_stay_usable() {
+        let mut tree = BTree::builder(4).check_invariants().key_sums().changefeed().build();
+        tree.extend(0..200_u64);
+
+        let mut right = tree.split_off(&120);
+        assert_eq!(tree.sum_range(..), (0..120).sum::<u64>());
+        assert_eq!(right.sum_range(150..), (150..200).sum::<u64>());
+        assert_eq!(tree.changes_since(200).unwrap().count(), 80);
+        assert!(tree.changes_since(200).unwrap()
+            .all(|change| change.change.kind == ChangeKind::Deleted && change.change.key >= 120));
+        assert_eq!(right.changes_since(0).unwrap().count(), 0);
+
+        assert!(tree.add(130).is_ok() && right.delete(&150).is_ok());
+        let rest = right.split_off(&180);
+        assert_eq!((tree.len(), right.len(), rest.len()), (121, 59, 20));
+        assert!(BTree::<u64>::new(3).split_off(&1).is_empty());
+    }
+
+    #[test]
+    fn split_at_rank_hands_off_the_rest() {
+        let mut tree = BTree::builder(4).check_invariants().build();
+        tree.extend((0..100).map(|k| k * 3));
+
+        let rest = tree.split_at_rank(40);
+        assert_eq!((tree.len(), rest.len()), (40, 60));
+        assert_eq!((tree.last(), rest.first()), (Some(117), Some(120)));
+        assert_eq!(rest.rank(&150), 10);
+
+        let mut page = rest;
+        let mut pages = Vec::new();
+        while !page.is_empty() {
+            let next = page.split_at_rank(25);
+            pages.push(page.len());
+            page = next;
+        }
+        assert_eq!(pages, [25, 25, 10]);
+        assert!(tree.split_at_rank(40).is_empty() && tree.split_at_rank(0).len() == 40);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn append_joins_disjoint_trees() {
+        let lengths = [(0, 5), (1, 1), (5, 0), (3, 200), (200, 3), (14, 30), (100, 100), (150, 2)];
+        for order in 3..10 {
+            for (left_len, right_len) in lengths {
+                let mut left = BTree::builder(order).check_invariants().build();
+                let mut right = BTree::builder(order).check_invariants().build();
+                left.extend(0..left_len);
+                right.extend(left_len..left_len + right_len);
+
+                left.append(right);
+                let msg = format!("order {} lengths {} {}", order, left_len, right_len);
+                let expected: Vec<usize> = (0..left_len + right_len).collect();
+                assert_eq!(left.iter().collect::<Vec<_>>(), expected, "{}", msg);
+            }
+        }
+    }
+
+    #[test]
+    fn append_undoes_split_off() {
+        let mut tree = BTree::builder(5).check_invariants().key_sums().changefeed().build();
+        tree.extend(0..300_u64);
+        let right = tree.split_off(&123);
+        let seq = tree.last_sequence();
+
+        tree.append(right);
+        assert_eq!(tree.len(), 300);
+        assert_eq!(tree.sum_range(100..200), (100..200).sum::<u64>());
+        assert_eq!(tree.changes_since(seq).unwrap().count(), 177);
+    }
+
+    #[test]
+    fn overlapping_trees_are_merged_key_by_key() {
+        let mut tree = BTree::builder(3).check_invariants().build();
+        tree.extend([1, 5, 9]);
+        tree.append((4..7).collect());
+        tree.append(BTree::builder(4).build());
+
+        let mut other = BTree::new(4);
+        other.extend(10..20);
+        tree.append(other);
+        let expected: Vec<usize> = [1, 4, 5, 6, 9].into_iter().chain(10..20).collect();
+        assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn append_keeps_to_the_memory_budget() {
+        let mut other: BTree<usize> = BTree::new(4);
+        other.extend(0..500);
+        let budget = other.memory_usage() / 2;
+
+        let mut empty = BTree::builder(4).check_invariants().memory_budget(budget).build();
+        empty.append(other.clone());
+        assert!(empty.memory_usage() <= budget);
+        assert!(!empty.is_empty() && empty.len() < 500);
+
+        let mut tree = BTree::builder(4).check_invariants().memory_budget(budget).build();
+        tree.extend([1000, 1001]);
+        tree.append(other);
+        assert!(tree.memory_usage() <= budget);
+        assert!(tree.len() < 502);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::node::NodeRef;
+use crate::BTree;
+use std::rc::Rc;
+
+impl<K> BTree<K> {
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Number of keys in the tree, kept up to date by every insert and delete
+    pub fn len(&self) -> usize {
+        self.key_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.key_count == 0
+    }
+
+    /// Number of levels in the tree, a tree made of only its root is 1 high.
+    /// Kept up to date as root splits and collapses change it
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of keys held by each node, grouped by level starting at the root
+    /// and ordered left to right within a level
+    pub fn key_counts_by_level(&self) -> Vec<Vec<usize>> {
+        let mut levels = Vec::new();
+        let mut level: Vec<NodeRef<K>> = vec![Rc::clone(&self.root)];
+
+        while !level.is_empty() {
+            levels.push(level.iter().map(|node| node.borrow().keys.len()).collect());
+            level = level.iter()
+                .flat_map(|node| node.borrow().children.clone())
+                .collect();
+        }
+
+        levels
+    }
+
+    /// Size and shape of the tree, see `TreeStats`. Walks every node
+    pub fn stats(&self) -> TreeStats
+        where K: Ord {
+        let levels = self.key_counts_by_level();
+        let capacity = (self.order - 1) as f64;
+        let fills: Vec<f64> = levels.iter().flatten().map(|keys| *keys as f64 / capacity).collect();
+
+        TreeStats {
+            node_count: self.node_count,
+            key_count: self.key_count,
+            height: self.height,
+            nodes_per_level: levels.iter().map(Vec::len).collect(),
+            min_fill: fills.iter().copied().fold(f64::INFINITY, f64::min),
+            max_fill: fills.iter().copied().fold(0.0, f64::max),
+            average_fill: fills.iter().sum::<f64>() / fills.len() as f64,
+            memory_bytes: self.memory_usage(),
+        }
+    }
+}
+
+/// Size and shape of a tree, returned by `BTree::stats`. A fill is the share of
+/// a node's `order - 1` key slots in use, the root included
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    pub node_count: usize,
+    pub key_count: usize,
+    pub height: usize,
+    /// Number of nodes on each level, starting at the root
+    pub nodes_per_level: Vec<usize>,
+    pub min_fill: f64,
+    pub max_fill: f64,
+    pub average_fill: f64,
+    /// Estimated bytes allocated for the nodes, see `BTree::memory_usage`
+    pub memory_bytes: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+
+    #[test]
+    fn single_node_tree() {
+        assert_eq!(BTree::<usize>::new(3).key_counts_by_level(), vec![vec![0]]);
+    }
+
+    #[test]
+    fn stats_describe_the_shape() {
+        let mut tree = BTree::new(5);
+        tree.extend(0..100);
+        let stats = tree.stats();
+
+        assert_eq!(stats.key_count, 100);
+        assert_eq!(stats.node_count, stats.nodes_per_level.iter().sum::<usize>());
+        assert_eq!(stats.nodes_per_level.len(), stats.height);
+        assert_eq!(stats.nodes_per_level[0], 1);
+        assert!(stats.min_fill >= 0.25 && stats.max_fill <= 1.0);
+        assert!(stats.min_fill <= stats.average_fill && stats.average_fill <= stats.max_fill);
+        assert_eq!(stats.memory_bytes, tree.memory_usage());
+
+        tree.compact().unwrap();
+        assert!(tree.stats().average_fill > stats.average_fill);
+        assert_eq!(BTree::<u8>::new(3).stats().max_fill, 0.0);
+    }
+
+    #[test]
+    fn counts_follow_splits() {
+        let mut tree = BTree::new(3);
+        for key in 1..=7 {
+            let _ = tree.add(key);
+        }
+
+        assert_eq!(tree.order(), 3);
+        assert_eq!(tree.height(), 3);
+        assert_eq!(
+            tree.key_counts_by_level(),
+            vec![vec![1], vec![1, 1], vec![1, 1, 1, 1]]);
+    }
+
+    #[test]
+    fn packed_appends_fill_the_nodes() {
+        let mut tree = BTree::builder(4).check_invariants().pack_appends().build();
+        tree.extend(1..=12);
+        assert_eq!(
+            tree.key_counts_by_level(),
+            vec![vec![3], vec![3, 3, 2, 1]]);
+
+        for order in 3..9 {
+            let mut packed = BTree::builder(order).check_invariants().pack_appends().build();
+            let mut plain = BTree::new(order);
+            packed.extend(0..1000);
+            plain.extend(0..1000);
+            assert!(packed.stats().average_fill > plain.stats().average_fill, "order {}", order);
+            assert!(packed.stats().average_fill > 0.85, "order {}", order);
+        }
+    }
+
+    #[test]
+    fn len_follows_adds_and_deletes() {
+        let mut tree = BTree::new(3);
+        assert!(tree.is_empty());
+
+        for key in 0..50 {
+            let _ = tree.add(key);
+        }
+        let _ = tree.add(10);
+        assert_eq!(tree.len(), 50);
+
+        for key in (0..50).step_by(2) {
+            let _ = tree.delete(&key);
+        }
+        let _ = tree.delete(&0);
+        assert_eq!(tree.len(), 25);
+
+        assert_eq!(BTree::from_slice(&[3, 1, 2, 3]).len(), 3);
+        assert!(BTree::<usize>::from_slice(&[]).is_empty());
+    }
+
+    #[test]
+    fn height_follows_splits_and_collapses() {
+        let mut tree = BTree::new(3);
+        assert_eq!(tree.height(), 1);
+
+        for key in 0..100 {
+            let _ = tree.add(key);
+            assert_eq!(tree.height(), tree.key_counts_by_level().len());
+        }
+        for key in 0..100 {
+            let _ = tree.delete(&key);
+            assert_eq!(tree.height(), tree.key_counts_by_level().len());
+        }
+        assert_eq!(tree.height(), 1);
+
+        let bulk = BTree::from_slice(&(0..1000).collect::<Vec<usize>>());
+        assert_eq!(bulk.height(), bulk.key_counts_by_level().len());
+    }
+}
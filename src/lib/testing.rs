@@ -0,0 +1,231 @@
+//! Differential testing against `std::collections::BTreeSet`.
+//!
+//! Only available with the `testing` feature. Random sequences of operations are
+//! applied to a `BTree` and a `BTreeSet` side by side, every result is compared
+//! and the tree's invariants are checked after each step. A failing sequence is
+//! shrunk to a short one that still fails before it is reported.
+//!
+//! ```
+//! use btree_rust::testing::{check_random, random_operations};
+//!
+//! check_random(4, 7, 500).unwrap();
+//! assert_eq!(random_operations(7, 500).len(), 500);
+//! ```
+
+use crate::BTree;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Keys are drawn from `0..KEY_SPACE` so inserts and deletes keep hitting
+/// keys that are already in the tree
+pub const KEY_SPACE: u16 = 512;
+
+/// A single step applied to both the tree and the reference set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Add(u16),
+    Delete(u16),
+    Contains(u16),
+    Range(u16, u16),
+    RemoveRange(u16, u16),
+    Clear,
+}
+
+/// The first step at which the tree and the set disagreed, or the tree broke
+/// one of its invariants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub order: usize,
+    /// The shortest failing sequence found, ending with the failing step
+    pub operations: Vec<Operation>,
+    pub message: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "order {}, after {:?}: {}", self.order, self.operations, self.message)
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Generate `count` operations from `seed`, mostly adds and deletes
+pub fn random_operations(seed: u64, count: usize) -> Vec<Operation> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        mix(state)
+    };
+
+    (0..count)
+        .map(|_| {
+            let kind = next() % 100;
+            let key = (next() % KEY_SPACE as u64) as u16;
+            match kind {
+                0..=44 => Operation::Add(key),
+                45..=79 => Operation::Delete(key),
+                80..=91 => Operation::Contains(key),
+                92..=95 => Operation::Range(key, key.saturating_add((next() % 64) as u16)),
+                96..=98 => Operation::RemoveRange(key, key.saturating_add((next() % 64) as u16)),
+                _ => Operation::Clear,
+            }
+        })
+        .collect()
+}
+
+/// Run `count` random operations from `seed` on a tree of the given order
+pub fn check_random(order: usize, seed: u64, count: usize) -> Result<(), Divergence> {
+    check_operations(order, &random_operations(seed, count))
+}
+
+/// Apply `operations` to a tree and a `BTreeSet`, comparing them after every step
+pub fn check_operations(order: usize, operations: &[Operation]) -> Result<(), Divergence> {
+    let (failed_at, _) = match run(order, operations) {
+        Ok(()) => return Ok(()),
+        Err(failure) => failure,
+    };
+
+    let operations = shrink(
+        operations[..=failed_at].to_vec(),
+        |candidate| run(order, candidate).err().map(|(failed_at, _)| failed_at));
+    let (_, message) = run(order, &operations).unwrap_err();
+    Err(Divergence { order, operations, message })
+}
+
+/// # Returns
+/// The index of the failing operation and what went wrong
+fn run(order: usize, operations: &[Operation]) -> Result<(), (usize, String)> {
+    let mut tree = BTree::new(order);
+    let mut set = BTreeSet::new();
+
+    for (idx, operation) in operations.iter().enumerate() {
+        let fail = |message: String| (idx, message);
+
+        match *operation {
+            Operation::Add(key) => {
+                let (tree_added, set_added) = (tree.add(key).is_ok(), set.insert(key));
+                if tree_added != set_added {
+                    return Err(fail(format!("add returned {}, expected {}", tree_added, set_added)));
+                }
+            }
+            Operation::Delete(key) => {
+                let (tree_deleted, set_deleted) = (tree.delete(&key).is_ok(), set.remove(&key));
+                if tree_deleted != set_deleted {
+                    return Err(fail(
+                        format!("delete returned {}, expected {}", tree_deleted, set_deleted)));
+                }
+            }
+            Operation::Contains(key) => {
+                if tree.contains(&key) != set.contains(&key) {
+                    return Err(fail(format!("contains disagrees for {}", key)));
+                }
+            }
+            Operation::Range(start, end) => {
+                let keys: Vec<u16> = tree.range(start..end).collect();
+                let expected: Vec<u16> = set.range(start..end).copied().collect();
+                if keys != expected {
+                    return Err(fail(format!("range gave {:?}, expected {:?}", keys, expected)));
+                }
+            }
+            Operation::RemoveRange(start, end) => {
+                let removed = tree.remove_range(start..end);
+                let expected = set.range(start..end).count();
+                set.retain(|key| !(start..end).contains(key));
+                if removed != expected {
+                    return Err(fail(format!("remove_range removed {}, expected {}", removed, expected)));
+                }
+            }
+            Operation::Clear => {
+                tree.clear();
+                set.clear();
+            }
+        }
+
+        if let Err(err) = tree.check_invariants() {
+            return Err(fail(err.to_string()));
+        }
+        if tree.len() != set.len() {
+            return Err(fail(format!("len is {}, expected {}", tree.len(), set.len())));
+        }
+        if (tree.first(), tree.last()) != (set.first().copied(), set.last().copied()) {
+            return Err(fail(String::from("first or last disagrees")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Drop chunks of operations, halving the chunk size down to single steps,
+/// for as long as `failing_step` still finds a failure in the sequence
+fn shrink(
+    operations: Vec<Operation>,
+    failing_step: impl Fn(&[Operation]) -> Option<usize>) -> Vec<Operation> {
+    let mut operations = operations;
+    let mut chunk = operations.len() / 2;
+
+    while chunk > 0 {
+        let mut start = 0;
+        while start < operations.len() {
+            let end = (start + chunk).min(operations.len());
+            let candidate: Vec<Operation> =
+                [&operations[..start], &operations[end..]].concat();
+
+            match failing_step(&candidate) {
+                Some(failed_at) => operations = candidate[..=failed_at].to_vec(),
+                None => start += chunk,
+            }
+        }
+        chunk /= 2;
+    }
+
+    operations
+}
+
+/// SplitMix64 finalizer
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_matches_btreeset() {
+        for order in [3, 4, 5, 8, 33] {
+            for seed in 0..20 {
+                if let Err(divergence) = check_random(order, seed, 2_000) {
+                    panic!("seed {}: {}", seed, divergence);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn operations_are_reproducible() {
+        assert_eq!(random_operations(3, 100), random_operations(3, 100));
+        assert_ne!(random_operations(3, 100), random_operations(4, 100));
+    }
+
+    #[test]
+    fn failures_are_shrunk() {
+        // pretend that deleting a key added earlier is a bug
+        let failing_step = |operations: &[Operation]| {
+            operations.iter().enumerate().position(|(idx, operation)| match operation {
+                Operation::Delete(key) => operations[..idx].contains(&Operation::Add(*key)),
+                _ => false,
+            })
+        };
+
+        let operations = random_operations(5, 1_000);
+        let failed_at = failing_step(&operations).unwrap();
+        let shrunk = shrink(operations[..=failed_at].to_vec(), failing_step);
+
+        assert_eq!(shrunk.len(), 2);
+        assert!(matches!(
+            shrunk[..],
+            [Operation::Add(added), Operation::Delete(deleted)] if added == deleted));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::observe::StructureObserver;
+use crate::BTree;
+use std::ops::{Bound, RangeBounds};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Inserted,
+    Deleted,
+    /// An equal key took the place of the stored one, see `BTree::insert`
+    Replaced,
+}
+
+/// A single key added to or removed from the tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change<K> {
+    pub key: K,
+    pub kind: ChangeKind,
+}
+
+/// Handle returned by `BTree::subscribe` and `BTree::observe`, used to unsubscribe again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(usize);
+
+type Callback<K> = Box<dyn FnMut(&Change<K>)>;
+
+struct Subscriber<K> {
+    id: SubscriptionId,
+    range: (Bound<K>, Bound<K>),
+    callback: Callback<K>,
+}
+
+/// The subscribers and structure observers registered on a tree
+pub(crate) struct Watchers<K> {
+    subscribers: Vec<Subscriber<K>>,
+    pub(crate) observers: Vec<(SubscriptionId, Box<dyn StructureObserver<K>>)>,
+    next_id: usize,
+}
+
+impl<K> Default for Watchers<K> {
+    fn default() -> Self {
+        Self { subscribers: Vec::new(), observers: Vec::new(), next_id: 0 }
+    }
+}
+
+impl<K> Watchers<K> {
+    pub(crate) fn next_id(&mut self) -> SubscriptionId {
+        self.next_id += 1;
+        SubscriptionId(self.next_id - 1)
+    }
+}
+
+impl<K: Ord> BTree<K> {
+    /// Call `callback` after every insert or delete of a key inside `range`.
+    /// To receive the changes on a channel, send them from the callback
+    pub fn subscribe<R, F>(&mut self, range: R, callback: F) -> SubscriptionId
+        where R: RangeBounds<K>, K: Clone, F: FnMut(&Change<K>) + 'static {
+        let id = self.watchers.next_id();
+
+        self.watchers.subscribers.push(Subscriber {
+            id,
+            range: (range.start_bound().cloned(), range.end_bound().cloned()),
+            callback: Box::new(callback),
+        });
+        id
+    }
+
+    /// Stop notifying a subscriber or observer, returns false if it was already removed
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let watchers = &mut self.watchers;
+        let count = watchers.subscribers.len() + watchers.observers.len();
+        watchers.subscribers.retain(|subscriber| subscriber.id != id);
+        watchers.observers.retain(|(observer_id, _)| *observer_id != id);
+        watchers.subscribers.len() + watchers.observers.len() != count
+    }
+
+    pub(crate) fn notify_watchers(&mut self, change: &Change<K>) {
+        self.watchers.subscribers.iter_mut()
+            .filter(|subscriber| subscriber.range.contains(&change.key))
+            .for_each(|subscriber| (subscriber.callback)(change));
+    }
+
+    /// Whether any subscriber or changefeed would see a change
+    pub(crate) fn is_observed(&self) -> bool {
+        !self.watchers.subscribers.is_empty() || self.changefeed.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    #[test]
+    fn only_changes_in_range_are_sent() {
+        let mut tree = BTree::new(3);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let seen_ref = Rc::clone(&seen);
+        tree.subscribe(10..20, move |change| seen_ref.borrow_mut().push(*change));
+
+        for key in [5, 10, 15, 20, 25] {
+            let _ = tree.add(key);
+        }
+        let _ = tree.delete(&15);
+        let _ = tree.delete(&25);
+
+        assert_eq!(*seen.borrow(), vec![
+            Change { key: 10, kind: ChangeKind::Inserted },
+            Change { key: 15, kind: ChangeKind::Inserted },
+            Change { key: 15, kind: ChangeKind::Deleted },
+        ]);
+    }
+
+    #[test]
+    fn failed_operations_are_not_sent() {
+        let mut tree = BTree::new(3);
+        let (sender, receiver) = mpsc::channel();
+        tree.subscribe(.., move |change| sender.send(*change).unwrap());
+
+        let _ = tree.add(1);
+        let _ = tree.add(1);
+        let _ = tree.delete(&2);
+
+        assert_eq!(receiver.try_iter().collect::<Vec<Change<usize>>>(), vec![
+            Change { key: 1, kind: ChangeKind::Inserted },
+        ]);
+    }
+
+    #[test]
+    fn unsubscribe_stops_notifications() {
+        let mut tree = BTree::new(3);
+        let count = Rc::new(RefCell::new(0));
+        let count_ref = Rc::clone(&count);
+        let id = tree.subscribe(..=100, move |_| *count_ref.borrow_mut() += 1);
+
+        let _ = tree.add(1);
+        assert!(tree.unsubscribe(id));
+        assert!(!tree.unsubscribe(id));
+        let _ = tree.add(2);
+
+        assert_eq!(*count.borrow(), 1);
+    }
+
+    thread_local! {
+        static CLONES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Counted(usize);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.with(|clones| clones.set(clones.get() + 1));
+            Counted(self.0)
+        }
+    }
+
+    #[test]
+    fn unobserved_edits_do_not_clone_keys() {
+        let mut tree = BTree::new(3);
+        for key in 0..50 {
+            tree.add(Counted(key)).unwrap();
+        }
+        let _ = tree.insert(Counted(60));
+        let _ = tree.insert(Counted(10));
+        let _ = tree.remove(&Counted(20));
+        tree.retain(|key| key.0 % 2 == 0);
+        assert_eq!(tree.drain().count(), 25);
+        tree.add(Counted(1)).unwrap();
+        tree.clear();
+        assert_eq!(CLONES.with(Cell::get), 0);
+
+        let (sender, receiver) = mpsc::channel();
+        tree.subscribe(.., move |change: &Change<Counted>| sender.send(change.key.0).unwrap());
+        tree.add(Counted(1)).unwrap();
+        let _ = tree.remove(&Counted(1));
+        assert_eq!(CLONES.with(Cell::get), 2);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), [1, 1]);
+    }
+}
@@ -1,45 +1,106 @@
-use btree_rust::BTree;
-
-fn main() {
-    let mut tree = BTree::new(4);
-    let _ = tree.add(0);
-    let _ = tree.add(5);
-    let _ = tree.add(10);
-    let _ = tree.add(15);
-    let _ = tree.add(20);
-    let _ = tree.add(25);
-    let _ = tree.add(30);
-    let _ = tree.add(35);
-    let _ = tree.add(40);
-    let _ = tree.add(45);
-    let _ = tree.add(31);
-    let _ = tree.add(32);
-
-    let res = tree.delete(35);
-
-
-    // let mut tree = BTree::new(4);
-    // let _ = tree.add(0);
-    // let _ = tree.add(5);
-    // let _ = tree.add(10);
-    // let _ = tree.add(15);
-    // let _ = tree.add(20);
-    // let _ = tree.add(25);
-    // let _ = tree.add(30);
-    // let _ = tree.add(35);
-    // let _ = tree.add(40);
-
-    // let _ = tree.add(45);
-    // let _ = tree.add(50);
-    // let _ = tree.add(55);
-    // let _ = tree.add(60);
-    // let _ = tree.add(65);
-    // let _ = tree.add(70);
-    // let _ = tree.add(75);
-
-    // let _ = tree.add(51);
-
-    // let _ = tree.delete(55);
-
-    // println!("Hello, world!");
+use btree_rust::{default_order, BTree};
+use std::env;
+use std::fs::File;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: btree_rust stats <dump-file> [--order <order>] [--binary]
+
+Commands:
+    stats    Load a key dump and print the height, node fill per level and
+             memory estimate of the resulting tree
+
+Options:
+    --order <order>    Order of the loaded tree (default: DEFAULT_ORDER)
+    --binary           Read a dump written by `dump_binary` instead of a text dump";
+
+/// Width of the histogram bars at 100% of a level's nodes
+const BAR_WIDTH: usize = 40;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("stats") => run_stats(&args[1..]),
+        _ => Err(USAGE.replace("DEFAULT_ORDER", &default_order::<usize>().to_string())),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_stats(args: &[String]) -> Result<(), String> {
+    let mut path: Option<&str> = None;
+    let mut order = default_order::<usize>();
+    let mut binary = false;
+
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--binary" => binary = true,
+            "--order" => {
+                order = args_iter.next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|value| *value >= 3)
+                    .ok_or("--order expects a number of at least 3")?;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    let path = path.ok_or("stats expects a dump file")?;
+    let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+
+    let tree: BTree<usize> = if binary {
+        BTree::load_binary(order, file)
+    } else {
+        BTree::load_text(order, file)
+    }.map_err(|err| format!("{}: {}", path, err))?;
+
+    print_stats(&tree);
+    Ok(())
+}
+
+fn print_stats<K: Ord>(tree: &BTree<K>) {
+    let levels = tree.key_counts_by_level();
+    let max_keys = tree.order() - 1;
+    let node_count: usize = levels.iter().map(Vec::len).sum();
+
+    println!("order:  {}", tree.order());
+    println!("height: {}", tree.height());
+    println!("nodes:  {}", node_count);
+    println!("keys:   {}", tree.len());
+    println!("memory: ~{} bytes", tree.memory_usage());
+
+    for (depth, level) in levels.iter().enumerate() {
+        let level_keys: usize = level.iter().sum();
+        println!();
+        println!(
+            "level {} ({} nodes, {:.0}% full)",
+            depth, level.len(), percent(level_keys, level.len() * max_keys));
+
+        // bucket each node by its fill in 10% steps, a full node lands in the last one
+        let mut buckets = [0_usize; 10];
+        for keys in level {
+            buckets[(keys * 10 / max_keys).min(9)] += 1;
+        }
+
+        for (bucket, count) in buckets.iter().enumerate() {
+            if *count == 0 { continue; }
+
+            let label = format!("{}-{}%", bucket * 10, bucket * 10 + 10);
+            let bar_len = (count * BAR_WIDTH).div_ceil(level.len());
+            println!("  {:>8} {:<width$} {}", label, "#".repeat(bar_len), count, width = BAR_WIDTH);
+        }
+    }
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 { 0.0 } else { part as f64 * 100.0 / whole as f64 }
 }
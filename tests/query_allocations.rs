@@ -0,0 +1,63 @@
+//! The lookups promise not to allocate, which is checked with an allocator
+//! counting the allocations made on each thread
+
+use btree_rust::{BTree, BTreeMap, SearchMode};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_in(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn lookups_do_not_allocate() {
+    for mode in [SearchMode::Binary, SearchMode::Interpolation] {
+        let mut tree = BTree::builder(5).search_mode(mode).build();
+        tree.extend((0..2000_u64).map(|k| k * 7 % 2003));
+        let expected: std::collections::BTreeSet<u64> = tree.iter().collect();
+
+        let allocations = allocations_in(|| {
+            for value in 0..2100 {
+                assert_eq!(tree.contains(&value), expected.contains(&value));
+                let _ = tree.get(&value);
+                let _ = tree.floor(&value);
+                let _ = tree.ceiling(&value);
+            }
+        });
+        assert_eq!(allocations, 0, "{:?}", mode);
+    }
+
+    let mut map = BTreeMap::new(4);
+    for key in 0..500_u32 {
+        let _ = map.add(key, key * 2);
+    }
+    assert_eq!(allocations_in(|| assert!((0..500).all(|key| map.get(&key) == Some(key * 2)))), 0);
+}
+
+#[test]
+fn the_counter_sees_allocations() {
+    let mut tree = BTree::new(3);
+    assert!(allocations_in(|| tree.extend(0..100_u64)) > 0);
+}